@@ -0,0 +1,68 @@
+//! Times generating full levels with the `Simple` generator.
+//!
+//! The game is a single binary, so the modules the generator needs are pulled
+//! in by path. Run with `cargo bench`.
+
+// The modules' unit tests get stripped here, leaving their imports unused.
+#![allow(dead_code, unused_imports)]
+
+extern crate ggez;
+extern crate rand;
+
+#[path = "../src/constants.rs"]
+mod constants;
+#[path = "../src/difficulty.rs"]
+mod difficulty;
+#[path = "../src/entities.rs"]
+mod entities;
+#[path = "../src/helpers.rs"]
+mod helpers;
+#[path = "../src/loot.rs"]
+mod loot;
+#[path = "../src/map/mod.rs"]
+mod map;
+#[path = "../src/messages.rs"]
+mod messages;
+#[path = "../src/spawns.rs"]
+mod spawns;
+#[path = "../src/tables.rs"]
+mod tables;
+#[path = "../src/tileset.rs"]
+mod tileset;
+
+use difficulty::Difficulty;
+use map::generation::{generate_map, Simple, MAP_GEN_OPTIONS};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200;
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+fn main() {
+    let difficulty = Difficulty::for_depth(1);
+
+    generate_map::<Simple>(MAP_GEN_OPTIONS, &difficulty);
+
+    let mut fastest = Duration::from_secs(u64::MAX);
+    let mut slowest = Duration::from_secs(0);
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        let time = Instant::now();
+        generate_map::<Simple>(MAP_GEN_OPTIONS, &difficulty);
+        let elapsed = time.elapsed();
+
+        fastest = fastest.min(elapsed);
+        slowest = slowest.max(elapsed);
+    }
+
+    println!(
+        "generate_map::<Simple> x{}: mean {:.3} ms, min {:.3} ms, max {:.3} ms",
+        ITERATIONS,
+        millis(start.elapsed()) / ITERATIONS as f64,
+        millis(fastest),
+        millis(slowest)
+    );
+}
@@ -511,6 +511,11 @@ pub const TILE_DOOR: TileDecl = TileDecl {
     x: 15,
     y: 7,
 };
+pub const TILE_DOOR_OPEN: TileDecl = TileDecl {
+    name: "door_open",
+    x: 7,
+    y: 1,
+};
 pub const TILE_POTION: TileDecl = TileDecl {
     name: "potion",
     x: 9,
@@ -521,8 +526,13 @@ pub const TILE_AXE: TileDecl = TileDecl {
     x: 8,
     y: 5,
 };
+pub const TILE_TRAP: TileDecl = TileDecl {
+    name: "trap",
+    x: 30,
+    y: 2,
+};
 
-const TILES: [TileDecl; 102] = [
+const TILES: [TileDecl; 104] = [
     TILE_SPACE,
     TILE_CAP_A,
     TILE_CAP_B,
@@ -623,8 +633,10 @@ const TILES: [TileDecl; 102] = [
     TILE_ROOM_SOLID,
     TILE_PATH,
     TILE_DOOR,
+    TILE_DOOR_OPEN,
     TILE_POTION,
     TILE_AXE,
+    TILE_TRAP,
 ];
 
 pub fn register_tiles(ts: &mut TileSet) -> Result<(), ()> {
@@ -0,0 +1,171 @@
+//! How much harder the dungeon gets the deeper it goes. Every number that
+//! changes with depth comes from here, so balancing the curve means editing
+//! the constants below and nothing else.
+
+use map::generation::MapGenOptions;
+
+/// Monster hit points grow by this many percent per level below the first.
+const MONSTER_HP_PERCENT_PER_DEPTH: i32 = 15;
+/// Monsters hit one point harder every this many levels.
+const DEPTHS_PER_MONSTER_ATK: i32 = 2;
+/// Rooms get one more roll on the spawn table every this many levels.
+const DEPTHS_PER_SPAWN_ROLL: i32 = 3;
+const MAX_SPAWN_ROLLS_PER_ROOM: u32 = 3;
+const ITEM_ROLLS_PER_ROOM: u32 = 2;
+/// Loot tables are rolled this many levels deeper per level, so better items
+/// show up sooner than the tables alone would have them.
+const LOOT_DEPTH_PER_DEPTH: i32 = 1;
+const GOLD_PER_DEPTH: u32 = 10;
+/// Wands get one more charge every this many levels.
+const DEPTHS_PER_WAND_CHARGE: i32 = 3;
+/// Rooms can grow one tile wider and taller every this many levels...
+const DEPTHS_PER_ROOM_GROWTH: i32 = 2;
+const MAX_ROOM_GROWTH: i32 = 4;
+/// ...and pack one tile closer together every this many.
+const DEPTHS_PER_ROOM_BUFFER_SHRINK: i32 = 4;
+const MIN_ROOM_BUFFER: i32 = 1;
+/// A wandering monster turns up once every this many turns at the surface,
+/// and this many turns sooner per level below it.
+const WANDERER_INTERVAL: u32 = 100;
+const WANDERER_INTERVAL_PER_DEPTH: u32 = 10;
+const MIN_WANDERER_INTERVAL: u32 = 40;
+
+#[derive(Debug, Clone)]
+pub struct Difficulty {
+    pub depth: i32,
+    /// Monster hit points, in percent of what they start with.
+    pub monster_hp_percent: i32,
+    /// Added to every monster's attack.
+    pub monster_atk_bonus: i32,
+    /// How many times each room rolls on the room spawn table.
+    pub spawn_rolls_per_room: u32,
+    /// How many times each room rolls on the floor loot table.
+    pub item_rolls_per_room: u32,
+    /// The depth loot tables are rolled at.
+    pub loot_depth: i32,
+    /// Added to every pile of gold.
+    pub gold_bonus: u32,
+    /// Added to the charges of every wand.
+    pub wand_charge_bonus: u32,
+    /// How much wider and taller rooms can get.
+    pub room_growth: i32,
+    /// How much closer together rooms can get.
+    pub room_buffer_shrink: i32,
+    /// Turns between wandering monsters.
+    pub wanderer_interval: u32,
+}
+
+impl Difficulty {
+    pub fn for_depth(depth: i32) -> Difficulty {
+        // How many levels below the first one.
+        let below = depth.max(1) - 1;
+
+        Difficulty {
+            depth,
+            monster_hp_percent: 100 + MONSTER_HP_PERCENT_PER_DEPTH * below,
+            monster_atk_bonus: below / DEPTHS_PER_MONSTER_ATK,
+            spawn_rolls_per_room: (1 + (below / DEPTHS_PER_SPAWN_ROLL) as u32)
+                .min(MAX_SPAWN_ROLLS_PER_ROOM),
+            item_rolls_per_room: ITEM_ROLLS_PER_ROOM,
+            loot_depth: depth + LOOT_DEPTH_PER_DEPTH * below,
+            gold_bonus: GOLD_PER_DEPTH * below as u32,
+            wand_charge_bonus: (below / DEPTHS_PER_WAND_CHARGE) as u32,
+            room_growth: (below / DEPTHS_PER_ROOM_GROWTH).min(MAX_ROOM_GROWTH),
+            room_buffer_shrink: below / DEPTHS_PER_ROOM_BUFFER_SHRINK,
+            wanderer_interval: WANDERER_INTERVAL
+                .saturating_sub(WANDERER_INTERVAL_PER_DEPTH * below as u32)
+                .max(MIN_WANDERER_INTERVAL),
+        }
+    }
+
+    pub fn monster_hp(&self, base: i32) -> i32 {
+        base * self.monster_hp_percent / 100
+    }
+
+    pub fn monster_atk(&self, base: i32) -> i32 {
+        base + self.monster_atk_bonus
+    }
+
+    /// `options`, laid out for this depth: rooms grow and move closer
+    /// together.
+    pub fn map_gen_options(&self, options: MapGenOptions) -> MapGenOptions {
+        MapGenOptions {
+            room_width: options.room_width.start..options.room_width.end + self.room_growth,
+            room_height: options.room_height.start..options.room_height.end + self.room_growth,
+            room_buffer: (options.room_buffer - self.room_buffer_shrink).max(MIN_ROOM_BUFFER),
+            ..options
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::generation::MAP_GEN_OPTIONS as OPTIONS;
+
+    #[test]
+    fn first_level_is_unscaled() {
+        let difficulty = Difficulty::for_depth(1);
+
+        assert_eq!(difficulty.monster_hp(30), 30);
+        assert_eq!(difficulty.monster_atk(5), 5);
+        assert_eq!(difficulty.spawn_rolls_per_room, 1);
+        assert_eq!(difficulty.loot_depth, 1);
+        assert_eq!(difficulty.gold_bonus, 0);
+        assert_eq!(difficulty.wand_charge_bonus, 0);
+        assert_eq!(difficulty.wanderer_interval, WANDERER_INTERVAL);
+
+        let options = difficulty.map_gen_options(OPTIONS);
+        assert_eq!(options.room_width, OPTIONS.room_width);
+        assert_eq!(options.room_height, OPTIONS.room_height);
+        assert_eq!(options.room_buffer, OPTIONS.room_buffer);
+    }
+
+    #[test]
+    fn depths_above_the_first_count_as_the_first() {
+        let (first, above) = (Difficulty::for_depth(1), Difficulty::for_depth(-3));
+
+        assert_eq!(above.monster_hp_percent, first.monster_hp_percent);
+        assert_eq!(above.spawn_rolls_per_room, first.spawn_rolls_per_room);
+        assert_eq!(above.wanderer_interval, first.wanderer_interval);
+    }
+
+    #[test]
+    fn deeper_is_never_easier() {
+        for depth in 1..50 {
+            let (this, next) = (Difficulty::for_depth(depth), Difficulty::for_depth(depth + 1));
+
+            assert!(next.monster_hp_percent > this.monster_hp_percent);
+            assert!(next.monster_atk_bonus >= this.monster_atk_bonus);
+            assert!(next.spawn_rolls_per_room >= this.spawn_rolls_per_room);
+            assert!(next.item_rolls_per_room >= this.item_rolls_per_room);
+            assert!(next.loot_depth > this.loot_depth);
+            assert!(next.gold_bonus > this.gold_bonus);
+            assert!(next.wand_charge_bonus >= this.wand_charge_bonus);
+            assert!(next.room_growth >= this.room_growth);
+            assert!(next.room_buffer_shrink >= this.room_buffer_shrink);
+            assert!(next.wanderer_interval <= this.wanderer_interval);
+        }
+    }
+
+    #[test]
+    fn caps_hold_deep_down() {
+        for depth in 1..1000 {
+            let difficulty = Difficulty::for_depth(depth);
+
+            assert!(difficulty.spawn_rolls_per_room <= MAX_SPAWN_ROLLS_PER_ROOM);
+            assert!(difficulty.room_growth <= MAX_ROOM_GROWTH);
+            assert!(difficulty.wanderer_interval >= MIN_WANDERER_INTERVAL);
+            assert!(difficulty.wanderer_interval > 0);
+
+            let options = difficulty.map_gen_options(OPTIONS);
+            assert!(options.room_buffer >= MIN_ROOM_BUFFER);
+            assert!(options.room_width.end > options.room_width.start);
+            assert!(options.room_height.end > options.room_height.start);
+            assert!(
+                options.map_width - options.outside_buffer - options.room_width.end
+                    > options.outside_buffer
+            );
+        }
+    }
+}
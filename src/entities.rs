@@ -1,18 +1,293 @@
+use ggez::graphics::Color;
 use helpers::Coords;
+use loot;
+use map::Map;
+use messages::{Event, EventType};
+use rand::thread_rng;
+
+/// Energy a creature gains every turn at normal speed.
+pub const NORMAL_SPEED: i32 = 100;
+/// Energy spent by taking an action.
+pub const ACTION_COST: i32 = 100;
+/// Turns it takes the player to regenerate one HP.
+const REGEN_INTERVAL: u32 = 10;
+/// XP needed to go from level 1 to 2. Every level after needs this much more.
+const XP_PER_LEVEL: u32 = 20;
+const MAX_HP_PER_LEVEL: i32 = 5;
+const ATK_PER_LEVEL: i32 = 2;
+/// Attack gained from a scroll of enchant weapon.
+const ENCHANT_ATK: i32 = 1;
+/// The most the player can eat, in turns of food.
+pub const MAX_SATIATION: i32 = 2000;
+const HUNGRY_AT: i32 = 300;
+const WEAK_AT: i32 = 150;
+const FAINTING_AT: i32 = 50;
+
+/// How badly the player needs to eat.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Hunger {
+    Fed,
+    Hungry,
+    /// Too hungry to heal naturally.
+    Weak,
+    /// Passes out every now and then.
+    Fainting,
+    Starved,
+}
+
+impl Hunger {
+    /// Label for the HUD, if the player should be told.
+    pub fn tag(&self) -> Option<&'static str> {
+        use self::Hunger::*;
+
+        match self {
+            Fed => None,
+            Hungry => Some("Hungry"),
+            Weak => Some("Weak"),
+            Fainting | Starved => Some("Fainting"),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        use self::Hunger::*;
+
+        match self {
+            Fed | Hungry => Color::from_rgb(191, 191, 0),
+            Weak => Color::from_rgb(255, 127, 0),
+            Fainting | Starved => Color::from_rgb(191, 0, 0),
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        use self::Hunger::*;
+
+        match self {
+            Fed => "You feel satiated.",
+            Hungry => "You are getting hungry.",
+            Weak => "You feel weak from hunger.",
+            Fainting => "You are fainting from hunger!",
+            Starved => "You starve.",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusKind {
+    /// Loses one HP every turn.
+    Poison,
+    /// Gains one HP every turn.
+    Regeneration,
+    /// Stumbles around at random half of the time.
+    Confusion,
+    /// Acts twice as often.
+    Haste,
+    /// Acts half as often.
+    Slow,
+}
+
+impl StatusKind {
+    /// Short label for the HUD.
+    pub fn tag(&self) -> &'static str {
+        use self::StatusKind::*;
+
+        match self {
+            Poison => "Psn",
+            Regeneration => "Rgn",
+            Confusion => "Cnf",
+            Haste => "Hst",
+            Slow => "Slw",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        use self::StatusKind::*;
+
+        match self {
+            Poison => Color::from_rgb(127, 191, 0),
+            Regeneration => Color::from_rgb(0, 191, 0),
+            Confusion => Color::from_rgb(191, 63, 191),
+            Haste => Color::from_rgb(0, 191, 255),
+            Slow => Color::from_rgb(127, 127, 191),
+        }
+    }
+
+    pub fn start_message(&self) -> &'static str {
+        use self::StatusKind::*;
+
+        match self {
+            Poison => "You are poisoned!",
+            Regeneration => "Your wounds begin to knit.",
+            Confusion => "You feel confused.",
+            Haste => "You feel quick.",
+            Slow => "You feel sluggish.",
+        }
+    }
+
+    pub fn end_message(&self) -> &'static str {
+        use self::StatusKind::*;
+
+        match self {
+            Poison => "The poison wears off.",
+            Regeneration => "Your wounds stop knitting.",
+            Confusion => "Your head clears.",
+            Haste => "You slow down.",
+            Slow => "You speed up.",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Turns left before it wears off.
+    pub turns: u32,
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, turns: u32) -> StatusEffect {
+        StatusEffect { kind, turns }
+    }
+}
 
 pub trait Creature {
     fn pos(&self) -> Coords;
+    fn set_pos(&mut self, pos: Coords);
+    fn name(&self) -> &'static str;
     fn tile_name(&self) -> &'static str;
     fn take_damage(&mut self, dmg: i32);
     fn deal_damage(&self, c: &mut Creature);
     fn is_dead(&self) -> bool;
     fn hp(&self) -> i32;
+    fn energy(&self) -> i32;
+    fn set_energy(&mut self, energy: i32);
+    fn effects(&self) -> &[StatusEffect];
+    fn effects_mut(&mut self) -> &mut Vec<StatusEffect>;
+
+    fn speed(&self) -> i32 {
+        let mut speed = NORMAL_SPEED;
+
+        if self.has_effect(StatusKind::Haste) {
+            speed *= 2;
+        }
+
+        if self.has_effect(StatusKind::Slow) {
+            speed /= 2;
+        }
+
+        speed
+    }
+
+    fn has_effect(&self, kind: StatusKind) -> bool {
+        self.effects().iter().any(|e| e.kind == kind)
+    }
+
+    /// Applies `effect`. If it is already active, it lasts for whichever
+    /// duration is longer.
+    fn add_effect(&mut self, effect: StatusEffect) {
+        let effects = self.effects_mut();
+
+        match effects.iter_mut().find(|e| e.kind == effect.kind) {
+            Some(active) => active.turns = active.turns.max(effect.turns),
+            None => effects.push(effect),
+        }
+    }
+
+    /// Applies one turn's worth of every active effect. Returns the effects
+    /// that wore off.
+    fn tick_effects(&mut self) -> Vec<StatusKind> {
+        let kinds: Vec<_> = self.effects().iter().map(|e| e.kind).collect();
+
+        for kind in kinds {
+            match kind {
+                StatusKind::Poison => self.take_damage(1),
+                StatusKind::Regeneration => self.take_damage(-1),
+                StatusKind::Confusion | StatusKind::Haste | StatusKind::Slow => {}
+            }
+        }
+
+        let effects = self.effects_mut();
+        let mut expired = Vec::new();
+
+        for effect in effects.iter_mut() {
+            effect.turns = effect.turns.saturating_sub(1);
+
+            if effect.turns == 0 {
+                expired.push(effect.kind);
+            }
+        }
+
+        effects.retain(|e| e.turns > 0);
+        expired
+    }
+
+    /// An effect this creature's attacks inflict on whatever they hit.
+    fn attack_effect(&self) -> Option<StatusEffect> {
+        None
+    }
+
+    /// Makes this creature's attacks stronger. Returns whether it had a weapon
+    /// to enchant.
+    fn enchant_weapon(&mut self) -> bool {
+        false
+    }
+
+    /// Eats something worth `nutrition` turns of food. Creatures that don't
+    /// get hungry ignore it.
+    fn feed(&mut self, _nutrition: i32) {}
+
+    /// The corpse this creature leaves behind, if it leaves one.
+    fn corpse(&self) -> Option<Corpse> {
+        None
+    }
+
+    /// Whether the creature is hurt enough to run from a fight.
+    fn wants_to_flee(&self) -> bool {
+        false
+    }
+
+    /// XP awarded for killing this creature.
+    fn xp_value(&self) -> u32 {
+        0
+    }
+
+    /// The loot table for what the creature drops when it dies.
+    fn drops(&self) -> &'static [loot::Entry] {
+        loot::MONSTER_DROPS
+    }
+}
+
+/// Items of the same kind, carried together. Never empty.
+pub struct ItemStack {
+    items: Vec<Box<Item>>,
+}
+
+impl ItemStack {
+    pub fn new(item: Box<Item>) -> ItemStack {
+        ItemStack { items: vec![item] }
+    }
+
+    /// The item the stack is made of.
+    pub fn item(&self) -> &Item {
+        &*self.items[0]
+    }
+
+    pub fn count(&self) -> usize {
+        self.items.len()
+    }
 }
 
 pub struct Player {
     pub pos: Coords,
-    pub inv: Vec<Box<Item>>,
+    pub inv: Vec<ItemStack>,
     pub hp: i32,
+    pub max_hp: i32,
+    pub energy: i32,
+    pub gold: u32,
+    pub atk: i32,
+    pub level: u32,
+    pub xp: u32,
+    pub effects: Vec<StatusEffect>,
+    pub satiation: i32,
 }
 
 impl Player {
@@ -21,8 +296,107 @@ impl Player {
             pos,
             inv: Vec::new(),
             hp: 30,
+            max_hp: 30,
+            energy: ACTION_COST,
+            gold: 0,
+            atk: 10,
+            level: 1,
+            xp: 0,
+            effects: Vec::new(),
+            satiation: MAX_SATIATION * 3 / 4,
+        }
+    }
+
+    /// Total XP needed to reach the next level.
+    pub fn next_level_xp(&self) -> u32 {
+        XP_PER_LEVEL * self.level * (self.level + 1) / 2
+    }
+
+    /// Adds `xp`, levelling up as many times as it is enough for. Returns the
+    /// number of levels gained.
+    pub fn gain_xp(&mut self, xp: u32) -> u32 {
+        let mut levels = 0;
+        self.xp += xp;
+
+        while self.xp >= self.next_level_xp() {
+            self.level += 1;
+            self.max_hp += MAX_HP_PER_LEVEL;
+            self.hp += MAX_HP_PER_LEVEL;
+            self.atk += ATK_PER_LEVEL;
+            levels += 1;
+        }
+
+        levels
+    }
+
+    pub fn regenerate(&mut self, turn: u32) {
+        if turn.is_multiple_of(REGEN_INTERVAL) && self.hp < self.max_hp
+            && self.hunger() < Hunger::Weak
+        {
+            self.hp += 1;
+        }
+    }
+
+    /// Puts `item` in the inventory, on top of a matching stack if there is
+    /// one.
+    pub fn add_item(&mut self, item: Box<Item>) {
+        match self.inv
+            .iter_mut()
+            .find(|s| item.stacks_with(s.item()))
+        {
+            Some(stack) => stack.items.push(item),
+            None => self.inv.push(ItemStack::new(item)),
+        }
+    }
+
+    /// Takes one item off the stack in slot `index`.
+    pub fn take_item(&mut self, index: usize) -> Option<Box<Item>> {
+        let item = self.inv.get_mut(index)?.items.pop();
+
+        if self.inv[index].items.is_empty() {
+            self.inv.remove(index);
+        }
+
+        item
+    }
+
+    /// Puts back an item taken from slot `index`, where it came from.
+    pub fn return_item(&mut self, index: usize, item: Box<Item>) {
+        let stacks = self.inv
+            .get(index)
+            .is_some_and(|s| item.stacks_with(s.item()));
+
+        if stacks {
+            self.inv[index].items.push(item);
+        } else {
+            let index = index.min(self.inv.len());
+            self.inv.insert(index, ItemStack::new(item));
+        }
+    }
+
+    /// How many items the player carries, counting every item in a stack.
+    pub fn item_count(&self) -> usize {
+        self.inv.iter().map(|s| s.count()).sum()
+    }
+
+    pub fn hunger(&self) -> Hunger {
+        match self.satiation {
+            s if s <= 0 => Hunger::Starved,
+            s if s <= FAINTING_AT => Hunger::Fainting,
+            s if s <= WEAK_AT => Hunger::Weak,
+            s if s <= HUNGRY_AT => Hunger::Hungry,
+            _ => Hunger::Fed,
         }
     }
+
+    /// Burns a turn's worth of food. Returns the new hunger stage if that
+    /// crossed into one.
+    pub fn digest(&mut self) -> Option<Hunger> {
+        let before = self.hunger();
+        self.satiation -= 1;
+
+        Some(self.hunger()).filter(|&h| h != before)
+    }
 }
 
 impl Creature for Player {
@@ -30,31 +404,67 @@ impl Creature for Player {
         self.pos
     }
 
+    fn set_pos(&mut self, pos: Coords) {
+        self.pos = pos;
+    }
+
+    fn name(&self) -> &'static str {
+        "Player"
+    }
+
     fn tile_name(&self) -> &'static str {
         "P"
     }
 
+    /// Negative damage heals, but never past `max_hp`.
     fn take_damage(&mut self, dmg: i32) {
-        self.hp -= dmg;
+        self.hp = (self.hp - dmg).min(self.max_hp);
     }
 
     fn deal_damage(&self, c: &mut Creature) {
-        c.take_damage(10);
+        c.take_damage(self.atk);
     }
 
     fn is_dead(&self) -> bool {
         self.hp <= 0
     }
 
+    fn enchant_weapon(&mut self) -> bool {
+        self.atk += ENCHANT_ATK;
+        true
+    }
+
+    fn feed(&mut self, nutrition: i32) {
+        self.satiation = (self.satiation + nutrition).min(MAX_SATIATION);
+    }
+
     fn hp(&self) -> i32 {
         self.hp
     }
+
+    fn energy(&self) -> i32 {
+        self.energy
+    }
+
+    fn set_energy(&mut self, energy: i32) {
+        self.energy = energy;
+    }
+
+    fn effects(&self) -> &[StatusEffect] {
+        &self.effects
+    }
+
+    fn effects_mut(&mut self) -> &mut Vec<StatusEffect> {
+        &mut self.effects
+    }
 }
 
 pub struct Goblin {
     pub pos: Coords,
     pub atk: i32,
     pub hp: i32,
+    pub energy: i32,
+    pub effects: Vec<StatusEffect>,
 }
 
 impl Goblin {
@@ -63,6 +473,8 @@ impl Goblin {
             pos,
             atk: 5,
             hp: 30,
+            energy: 0,
+            effects: Vec::new(),
         }
     }
 }
@@ -72,6 +484,14 @@ impl Creature for Goblin {
         self.pos
     }
 
+    fn set_pos(&mut self, pos: Coords) {
+        self.pos = pos;
+    }
+
+    fn name(&self) -> &'static str {
+        "Goblin"
+    }
+
     fn tile_name(&self) -> &'static str {
         "G"
     }
@@ -91,12 +511,242 @@ impl Creature for Goblin {
     fn hp(&self) -> i32 {
         self.hp
     }
+
+    fn energy(&self) -> i32 {
+        self.energy
+    }
+
+    fn set_energy(&mut self, energy: i32) {
+        self.energy = energy;
+    }
+
+    fn effects(&self) -> &[StatusEffect] {
+        &self.effects
+    }
+
+    fn effects_mut(&mut self) -> &mut Vec<StatusEffect> {
+        &mut self.effects
+    }
+
+    fn wants_to_flee(&self) -> bool {
+        self.hp <= 10
+    }
+
+    fn xp_value(&self) -> u32 {
+        10
+    }
+
+    fn corpse(&self) -> Option<Corpse> {
+        Some(Corpse {
+            name: "Goblin corpse",
+            poisonous: false,
+        })
+    }
+}
+
+pub struct CaveSpider {
+    pub pos: Coords,
+    pub atk: i32,
+    pub hp: i32,
+    pub energy: i32,
+    pub effects: Vec<StatusEffect>,
+}
+
+impl CaveSpider {
+    pub fn new(pos: Coords) -> CaveSpider {
+        CaveSpider {
+            pos,
+            atk: 2,
+            hp: 12,
+            energy: 0,
+            effects: Vec::new(),
+        }
+    }
+}
+
+impl Creature for CaveSpider {
+    fn pos(&self) -> Coords {
+        self.pos
+    }
+
+    fn set_pos(&mut self, pos: Coords) {
+        self.pos = pos;
+    }
+
+    fn name(&self) -> &'static str {
+        "Cave spider"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "s"
+    }
+
+    fn take_damage(&mut self, dmg: i32) {
+        self.hp -= dmg;
+    }
+
+    fn deal_damage(&self, c: &mut Creature) {
+        c.take_damage(self.atk);
+    }
+
+    fn is_dead(&self) -> bool {
+        self.hp <= 0
+    }
+
+    fn hp(&self) -> i32 {
+        self.hp
+    }
+
+    fn energy(&self) -> i32 {
+        self.energy
+    }
+
+    fn set_energy(&mut self, energy: i32) {
+        self.energy = energy;
+    }
+
+    fn effects(&self) -> &[StatusEffect] {
+        &self.effects
+    }
+
+    fn effects_mut(&mut self) -> &mut Vec<StatusEffect> {
+        &mut self.effects
+    }
+
+    fn attack_effect(&self) -> Option<StatusEffect> {
+        Some(StatusEffect::new(StatusKind::Poison, 5))
+    }
+
+    fn xp_value(&self) -> u32 {
+        8
+    }
+
+    fn corpse(&self) -> Option<Corpse> {
+        Some(Corpse {
+            name: "Cave spider corpse",
+            poisonous: true,
+        })
+    }
+
+    /// Spiders don't carry anything.
+    fn drops(&self) -> &'static [loot::Entry] {
+        &[]
+    }
+}
+
+/// Kinds of magic items whose looks get shuffled every run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemClass {
+    Potion,
+    Scroll,
+}
+
+impl ItemClass {
+    /// The ids of every item kind in this class.
+    pub fn ids(&self) -> Vec<u32> {
+        item_kinds()
+            .iter()
+            .filter(|item| item.class() == Some(*self))
+            .map(|item| item.id())
+            .collect()
+    }
+}
+
+/// One of every kind of item there is. New items go in here, or they won't
+/// get a shuffled appearance; `loot`'s tests check everything the loot tables
+/// and monsters hand out is listed.
+pub fn item_kinds() -> Vec<Box<Item>> {
+    vec![
+        Box::new(HealingPotion),
+        Box::new(ThrowingKnife),
+        Box::new(RegenerationPotion),
+        Box::new(HastePotion),
+        Box::new(ConfusionPotion),
+        Box::new(Ration),
+        Box::new(Corpse {
+            name: "Corpse",
+            poisonous: false,
+        }),
+        Box::new(TeleportScroll),
+        Box::new(MagicMappingScroll),
+        Box::new(EnchantWeaponScroll),
+        Box::new(WandOfFireBolt { charges: 0 }),
+        Box::new(WandOfSlowMonster { charges: 0 }),
+        Box::new(Gold { amount: 0 }),
+    ]
+}
+
+/// What an item gets to work with when it is used.
+pub struct ItemContext<'a> {
+    pub user: &'a mut Creature,
+    pub map: &'a mut Map,
+    pub events: &'a mut Vec<Event>,
+    /// Where the item was aimed, for items that need a target.
+    pub target: Option<Coords>,
+}
+
+impl<'a> ItemContext<'a> {
+    fn message(&mut self, msg: &str, ty: EventType) {
+        self.events.push(Event::new(String::from(msg), ty));
+    }
 }
 
 pub trait Item {
     fn id(&self) -> u32;
+    fn name(&self) -> &'static str;
     fn tile_name(&self) -> &'static str;
-    fn consume(&mut self, creature: &mut Creature);
+    fn consume(&mut self, ctx: &mut ItemContext);
+
+    /// Whether using the item means picking a target first.
+    fn needs_target(&self) -> bool {
+        false
+    }
+
+    /// Whether the item is gone once used. Wands stick around.
+    fn is_spent(&self) -> bool {
+        true
+    }
+
+    /// Charges left, for items that have them.
+    fn charges(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the item can share an inventory slot with `other`.
+    fn stacks_with(&self, other: &Item) -> bool {
+        self.id() == other.id() && self.name() == other.name() && self.charges().is_none()
+            && other.charges().is_none()
+    }
+
+    /// Whether the item breaks when thrown, splashing whoever is close by.
+    fn shatters(&self) -> bool {
+        false
+    }
+
+    /// What happens to a creature caught in the splash when the item shatters.
+    fn splash(&self, _creature: &mut Creature) {}
+
+    /// The class this item belongs to, if it starts out unidentified.
+    fn class(&self) -> Option<ItemClass> {
+        None
+    }
+
+    /// The damage this item deals when thrown at a creature, or `None` if it
+    /// isn't meant to be thrown.
+    fn thrown_damage(&self) -> Option<i32> {
+        None
+    }
+
+    /// What the item is worth in gold. Shops won't take worthless items.
+    fn value(&self) -> u32 {
+        0
+    }
+
+    /// How much gold a pile of gold holds. Gold goes straight into the
+    /// player's purse instead of the inventory.
+    fn gold(&self) -> Option<u32> {
+        None
+    }
 }
 
 pub struct HealingPotion;
@@ -106,11 +756,480 @@ impl Item for HealingPotion {
         0
     }
 
+    fn name(&self) -> &'static str {
+        "Healing potion"
+    }
+
     fn tile_name(&self) -> &'static str {
         "potion"
     }
 
-    fn consume(&mut self, creature: &mut Creature) {
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        self.splash(ctx.user);
+    }
+
+    fn class(&self) -> Option<ItemClass> {
+        Some(ItemClass::Potion)
+    }
+
+    fn shatters(&self) -> bool {
+        true
+    }
+
+    fn splash(&self, creature: &mut Creature) {
         creature.take_damage(-10);
     }
+
+    fn value(&self) -> u32 {
+        40
+    }
+}
+
+pub struct ThrowingKnife;
+
+impl Item for ThrowingKnife {
+    fn id(&self) -> u32 {
+        1
+    }
+
+    fn name(&self) -> &'static str {
+        "Throwing knife"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "/"
+    }
+
+    fn consume(&mut self, _ctx: &mut ItemContext) {}
+
+    fn thrown_damage(&self) -> Option<i32> {
+        Some(8)
+    }
+
+    fn value(&self) -> u32 {
+        10
+    }
+}
+
+pub struct RegenerationPotion;
+
+impl Item for RegenerationPotion {
+    fn id(&self) -> u32 {
+        2
+    }
+
+    fn name(&self) -> &'static str {
+        "Potion of regeneration"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "potion"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        self.splash(ctx.user);
+    }
+
+    fn class(&self) -> Option<ItemClass> {
+        Some(ItemClass::Potion)
+    }
+
+    fn shatters(&self) -> bool {
+        true
+    }
+
+    fn splash(&self, creature: &mut Creature) {
+        creature.add_effect(StatusEffect::new(StatusKind::Regeneration, 20));
+    }
+
+    fn value(&self) -> u32 {
+        50
+    }
+}
+
+pub struct HastePotion;
+
+impl Item for HastePotion {
+    fn id(&self) -> u32 {
+        3
+    }
+
+    fn name(&self) -> &'static str {
+        "Potion of haste"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "potion"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        self.splash(ctx.user);
+    }
+
+    fn class(&self) -> Option<ItemClass> {
+        Some(ItemClass::Potion)
+    }
+
+    fn shatters(&self) -> bool {
+        true
+    }
+
+    fn splash(&self, creature: &mut Creature) {
+        creature.add_effect(StatusEffect::new(StatusKind::Haste, 15));
+    }
+
+    fn value(&self) -> u32 {
+        60
+    }
+}
+
+pub struct ConfusionPotion;
+
+impl Item for ConfusionPotion {
+    fn id(&self) -> u32 {
+        4
+    }
+
+    fn name(&self) -> &'static str {
+        "Potion of confusion"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "potion"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        self.splash(ctx.user);
+    }
+
+    fn class(&self) -> Option<ItemClass> {
+        Some(ItemClass::Potion)
+    }
+
+    fn shatters(&self) -> bool {
+        true
+    }
+
+    fn splash(&self, creature: &mut Creature) {
+        creature.add_effect(StatusEffect::new(StatusKind::Confusion, 10));
+    }
+
+    fn value(&self) -> u32 {
+        20
+    }
+}
+
+pub struct Ration;
+
+impl Item for Ration {
+    fn id(&self) -> u32 {
+        5
+    }
+
+    fn name(&self) -> &'static str {
+        "Ration"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "%"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        ctx.user.feed(800);
+    }
+
+    fn value(&self) -> u32 {
+        15
+    }
+}
+
+/// What's left of a slain monster. Filling, if not always wholesome.
+pub struct Corpse {
+    pub name: &'static str,
+    pub poisonous: bool,
+}
+
+impl Item for Corpse {
+    fn id(&self) -> u32 {
+        6
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "%"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        ctx.user.feed(300);
+
+        if self.poisonous {
+            ctx.user.add_effect(StatusEffect::new(StatusKind::Poison, 5));
+        }
+    }
+}
+
+pub struct TeleportScroll;
+
+impl Item for TeleportScroll {
+    fn id(&self) -> u32 {
+        7
+    }
+
+    fn name(&self) -> &'static str {
+        "Scroll of teleportation"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "?"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        let from = ctx.user.pos();
+
+        if let Some(pos) = ctx.map.random_walkable_tile(from, &mut thread_rng()) {
+            ctx.user.set_pos(pos);
+        }
+
+        ctx.message("You feel very jumpy.", EventType::Item);
+    }
+
+    fn class(&self) -> Option<ItemClass> {
+        Some(ItemClass::Scroll)
+    }
+
+    fn value(&self) -> u32 {
+        60
+    }
+}
+
+pub struct MagicMappingScroll;
+
+impl Item for MagicMappingScroll {
+    fn id(&self) -> u32 {
+        8
+    }
+
+    fn name(&self) -> &'static str {
+        "Scroll of magic mapping"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "?"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        ctx.map.reveal();
+        ctx.message("A map coalesces in your mind!", EventType::Item);
+    }
+
+    fn class(&self) -> Option<ItemClass> {
+        Some(ItemClass::Scroll)
+    }
+
+    fn value(&self) -> u32 {
+        80
+    }
+}
+
+pub struct EnchantWeaponScroll;
+
+impl Item for EnchantWeaponScroll {
+    fn id(&self) -> u32 {
+        9
+    }
+
+    fn name(&self) -> &'static str {
+        "Scroll of enchant weapon"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "?"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        if ctx.user.enchant_weapon() {
+            ctx.message("Your weapon glows blue for a moment.", EventType::Item);
+        } else {
+            ctx.message("Your hands twitch.", EventType::Item);
+        }
+    }
+
+    fn class(&self) -> Option<ItemClass> {
+        Some(ItemClass::Scroll)
+    }
+
+    fn value(&self) -> u32 {
+        100
+    }
+}
+
+/// Damage dealt by a bolt of fire.
+const FIRE_BOLT_DAMAGE: i32 = 12;
+
+pub struct WandOfFireBolt {
+    pub charges: u32,
+}
+
+impl Item for WandOfFireBolt {
+    fn id(&self) -> u32 {
+        10
+    }
+
+    fn name(&self) -> &'static str {
+        "Wand of fire bolt"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "-"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        let target = match (self.charges, ctx.target) {
+            (0, _) | (_, None) => return ctx.message("Nothing happens.", EventType::Item),
+            (_, Some(target)) => target,
+        };
+
+        self.charges -= 1;
+
+        let msg = match ctx.map.bolt_target(ctx.user.pos(), target) {
+            Some(monster) => {
+                monster.take_damage(FIRE_BOLT_DAMAGE);
+                format!("The bolt of fire burns the {}.", monster.name())
+            }
+            None => String::from("The bolt of fire hits nothing."),
+        };
+
+        ctx.events.push(Event::new(msg, EventType::Combat));
+    }
+
+    fn needs_target(&self) -> bool {
+        true
+    }
+
+    fn is_spent(&self) -> bool {
+        false
+    }
+
+    fn charges(&self) -> Option<u32> {
+        Some(self.charges)
+    }
+
+    /// Only the charges are worth anything, so empty wands won't sell.
+    fn value(&self) -> u32 {
+        30 * self.charges
+    }
+}
+
+pub struct WandOfSlowMonster {
+    pub charges: u32,
+}
+
+impl Item for WandOfSlowMonster {
+    fn id(&self) -> u32 {
+        11
+    }
+
+    fn name(&self) -> &'static str {
+        "Wand of slow monster"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "-"
+    }
+
+    fn consume(&mut self, ctx: &mut ItemContext) {
+        let target = match (self.charges, ctx.target) {
+            (0, _) | (_, None) => return ctx.message("Nothing happens.", EventType::Item),
+            (_, Some(target)) => target,
+        };
+
+        self.charges -= 1;
+
+        let msg = match ctx.map.bolt_target(ctx.user.pos(), target) {
+            Some(monster) => {
+                monster.add_effect(StatusEffect::new(StatusKind::Slow, 20));
+                format!("The {} slows down.", monster.name())
+            }
+            None => String::from("The bolt misses."),
+        };
+
+        ctx.events.push(Event::new(msg, EventType::Status));
+    }
+
+    fn needs_target(&self) -> bool {
+        true
+    }
+
+    fn is_spent(&self) -> bool {
+        false
+    }
+
+    fn charges(&self) -> Option<u32> {
+        Some(self.charges)
+    }
+
+    /// Only the charges are worth anything, so empty wands won't sell.
+    fn value(&self) -> u32 {
+        20 * self.charges
+    }
+}
+
+pub struct Gold {
+    pub amount: u32,
+}
+
+impl Item for Gold {
+    fn id(&self) -> u32 {
+        12
+    }
+
+    fn name(&self) -> &'static str {
+        "Gold"
+    }
+
+    fn tile_name(&self) -> &'static str {
+        "$"
+    }
+
+    fn consume(&mut self, _ctx: &mut ItemContext) {}
+
+    fn gold(&self) -> Option<u32> {
+        Some(self.amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn item_kinds_have_unique_ids() {
+        let kinds = item_kinds();
+        let ids: HashSet<_> = kinds.iter().map(|item| item.id()).collect();
+
+        assert_eq!(ids.len(), kinds.len());
+    }
+
+    #[test]
+    fn wands_are_worth_their_charges() {
+        assert_eq!(WandOfFireBolt { charges: 0 }.value(), 0);
+        assert_eq!(WandOfSlowMonster { charges: 0 }.value(), 0);
+        assert!(WandOfFireBolt { charges: 2 }.value() < WandOfFireBolt { charges: 5 }.value());
+    }
+
+    #[test]
+    fn classes_cover_their_items() {
+        for &class in [ItemClass::Potion, ItemClass::Scroll].iter() {
+            let ids = class.ids();
+            assert!(!ids.is_empty());
+
+            for item in item_kinds() {
+                assert_eq!(item.class() == Some(class), ids.contains(&item.id()));
+            }
+        }
+    }
 }
@@ -0,0 +1,1666 @@
+//! The state of a single run, and the rules it plays by.
+
+use constants;
+use difficulty::Difficulty;
+use entities::{Creature, Hunger, Item, ItemContext, Player, StatusKind, ACTION_COST};
+use ggez::{event, graphics::Color};
+use helpers::{clamp, Coords};
+use identification::{self, Identification};
+use loot;
+use map::{
+    generation::{generate_map, Simple, MAP_GEN_OPTIONS}, pathfinding::{DijkstraMap, PathGrid}, Map,
+    TileType, TrapKind,
+};
+use messages::{Event, EventType};
+use rand::{thread_rng, Rng};
+use spawns;
+use std::{
+    collections::BTreeMap, time::{Duration, Instant},
+};
+use tileset::TileSet;
+use {
+    ACTIVITY_STEP_DELAY, DISPLAY_MAP_HEIGHT, DISPLAY_MAP_WIDTH, MAP_HEIGHT, MAP_WIDTH, SIGHT_RADIUS,
+};
+
+/// What to do once a target is picked.
+#[derive(Debug, Clone, Copy)]
+pub enum TargetAction {
+    /// Throw the first throwing weapon in the inventory.
+    Fire,
+    /// Throw the item in this inventory slot.
+    Throw(usize),
+    /// Aim the item in this inventory slot, like a wand.
+    Zap(usize),
+}
+
+pub struct Targeting {
+    /// Monsters in sight, nearest first, to cycle through.
+    pub targets: Vec<Coords>,
+    pub selected: usize,
+    /// The tile being aimed at.
+    pub cursor: Coords,
+    pub action: TargetAction,
+}
+
+impl Targeting {
+    /// Starts out aimed at the nearest of `targets`, or at `origin` if there
+    /// are none.
+    pub fn new(targets: Vec<Coords>, origin: Coords, action: TargetAction) -> Targeting {
+        Targeting {
+            cursor: targets.first().cloned().unwrap_or(origin),
+            targets,
+            selected: 0,
+            action,
+        }
+    }
+
+    pub fn target(&self) -> Coords {
+        self.cursor
+    }
+
+    pub fn next(&mut self) {
+        if !self.targets.is_empty() {
+            self.selected = (self.selected + 1) % self.targets.len();
+            self.cursor = self.targets[self.selected];
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.targets.is_empty() {
+            self.selected = (self.selected + self.targets.len() - 1) % self.targets.len();
+            self.cursor = self.targets[self.selected];
+        }
+    }
+}
+
+/// A fainting player passes out on average once every this many turns.
+const FAINT_CHANCE: u32 = 15;
+/// Turns lost to passing out.
+const FAINT_TURNS: i32 = 3;
+/// How long monsters keep hunting the player after an alarm goes off.
+const ALARM_TURNS: u32 = 50;
+/// The four orthogonal steps.
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Something the player keeps doing over several turns, one step per tick,
+/// until it finishes or gets interrupted.
+pub enum Activity {
+    Explore,
+    /// The remaining path, with the next step last.
+    Travel(Vec<Coords>),
+    /// The direction the player is running in.
+    Run(i32, i32),
+    Rest,
+}
+
+impl Activity {
+    /// How long to wait between steps so the player can follow what happens.
+    pub fn step_delay(&self) -> Duration {
+        match self {
+            Activity::Rest => Duration::from_millis(0),
+            _ => ACTIVITY_STEP_DELAY,
+        }
+    }
+}
+
+pub struct Game {
+    pub map: Map,
+    pub look_cursor: Option<Coords>,
+    pub targeting: Option<Targeting>,
+    pub activity: Option<Activity>,
+    pub last_step: Instant,
+    pub turn: u32,
+    pub depth: i32,
+    /// How many monsters of each kind the player killed.
+    pub kills: BTreeMap<&'static str, u32>,
+    /// What killed the player, once the run is over.
+    pub cause_of_death: Option<String>,
+    pub player: Player,
+    pub events: Vec<Event>,
+    pub identification: Identification,
+    /// Until this turn, monsters know where the player is without seeing them.
+    pub alerted_until: u32,
+    /// Set when the player bumps into a shopkeeper, until the shop is opened.
+    pub shopping: bool,
+}
+
+/// Generates a fresh level at `depth` and picks where the player starts on
+/// it: the middle of the first room.
+fn new_level(depth: i32) -> (Map, Coords) {
+    let difficulty = Difficulty::for_depth(depth);
+    let mut map = generate_map::<Simple>(difficulty.map_gen_options(MAP_GEN_OPTIONS), &difficulty);
+    let player_position = match map.rooms.first() {
+        Some(room) => room.center().into(),
+        None => map.iter()
+            .find(|t| t.tile_type.is_walkable_tile())
+            .map_or(Coords::new(0, 0), |t| t.pos),
+    };
+
+    map.update_fov(player_position, SIGHT_RADIUS);
+
+    (map, player_position)
+}
+
+impl Game {
+    pub fn new() -> Game {
+        let (map, player_position) = new_level(1);
+
+        Game {
+            map,
+            look_cursor: None,
+            targeting: None,
+            activity: None,
+            last_step: Instant::now(),
+            turn: 0,
+            depth: 1,
+            kills: BTreeMap::new(),
+            cause_of_death: None,
+            player: Player::new(player_position),
+            events: Vec::new(),
+            identification: Identification::new(&mut thread_rng()),
+            alerted_until: 0,
+            shopping: false,
+        }
+    }
+
+    /// How tough the current level is.
+    pub fn difficulty(&self) -> Difficulty {
+        Difficulty::for_depth(self.depth)
+    }
+
+    pub fn total_kills(&self) -> u32 {
+        self.kills.values().sum()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.cause_of_death.is_some()
+    }
+
+    /// Ends the run if the player is dead.
+    fn check_player_death(&mut self, cause: String) {
+        if self.cause_of_death.is_none() && self.player.is_dead() {
+            self.activity = None;
+            self.targeting = None;
+            self.look_cursor = None;
+            self.cause_of_death = Some(cause);
+        }
+    }
+
+    /// Throws the current level away for a freshly generated one, keeping the
+    /// player where they stand. Handy for eyeballing the generator.
+    pub fn regenerate_level(&mut self) {
+        #[cfg(debug_assertions)]
+        let time = Instant::now();
+
+        let difficulty = self.difficulty();
+        self.map = generate_map::<Simple>(difficulty.map_gen_options(MAP_GEN_OPTIONS), &difficulty);
+
+        let player_position = self.player.pos;
+        self.map.monsters.retain(|m| m.pos() != player_position);
+        self.map.update_fov(player_position, SIGHT_RADIUS);
+
+        debugln!("Generation took: {} ms", time.elapsed().subsec_millis());
+    }
+
+    /// Advances the current activity when its step delay has passed.
+    pub fn update(&mut self) {
+        let step_delay = self.activity.as_ref().map(|a| a.step_delay());
+
+        if let Some(step_delay) = step_delay {
+            if self.last_step.elapsed() >= step_delay {
+                self.last_step = Instant::now();
+                self.continue_activity();
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ts: &mut TileSet) {
+        let player_position = self.player.pos;
+        let camera_position = self.camera();
+        let map = &self.map;
+
+        map.draw(
+            ts,
+            |t| {
+                t.pos >= camera_position && !t.tile_type.is_empty() && map.is_explored(t.pos)
+                    && (t.tile_type == TileType::Pathway || t.pos != player_position)
+            },
+            camera_position,
+        ).unwrap();
+
+        let player_position = Coords::new(
+            player_position.x - camera_position.x,
+            player_position.y - camera_position.y,
+        );
+
+        ts.queue_tile(
+            constants::TILE_SPEC_AT.name,
+            (player_position.x, player_position.y),
+            None,
+        ).unwrap();
+
+        let player = &self.player;
+        let hp = format!("HP: {}/{} ", player.hp, player.max_hp);
+        let mut x = hp.len() as i32;
+        ts.queue_string(&hp, (0, 0), None, None);
+
+        for effect in player.effects.iter() {
+            ts.queue_string(effect.kind.tag(), (x, 0), None, Some(effect.kind.color()));
+            x += effect.kind.tag().len() as i32 + 1;
+        }
+
+        if let Some(tag) = player.hunger().tag() {
+            ts.queue_string(tag, (x, 0), None, Some(player.hunger().color()));
+            x += tag.len() as i32 + 1;
+        }
+
+        ts.queue_string(
+            &format!(
+                "Lv: {} XP: {}/{} $: {}",
+                player.level,
+                player.xp,
+                player.next_level_xp(),
+                player.gold
+            ),
+            (x, 0),
+            None,
+            None,
+        );
+
+        if let Some(cursor) = self.look_cursor {
+            self.draw_look(ts, cursor);
+        }
+
+        self.draw_targeting(ts);
+
+        self.draw_events(ts);
+    }
+
+    /// Uses the item at `index` in the player's inventory, first asking for a
+    /// target if it needs one. Returns whether the item could be used.
+    pub fn use_item(&mut self, index: usize) -> bool {
+        let (throwable, needs_target) = match self.player.inv.get(index) {
+            Some(stack) => (stack.item().thrown_damage().is_some(), stack.item().needs_target()),
+            None => return false,
+        };
+
+        if throwable {
+            return false;
+        }
+
+        if needs_target {
+            self.start_targeting(TargetAction::Zap(index));
+            return self.targeting.is_some();
+        }
+
+        self.apply_item(index, None);
+        true
+    }
+
+    fn apply_item(&mut self, index: usize, target: Option<Coords>) {
+        let p_pos = self.player.pos;
+        let p_hp = self.player.hp;
+        let p_effects = self.player.effects.clone();
+        let p_satiation = self.player.satiation;
+        let mut item = match self.player.take_item(index) {
+            Some(item) => item,
+            None => return,
+        };
+        let unknown_name = self.identification.name(&*item);
+
+        item.consume(&mut ItemContext {
+            user: &mut self.player,
+            map: &mut self.map,
+            events: &mut self.events,
+            target,
+        });
+
+        if self.identification.identify(&*item) {
+            self.events.push(Event::new(
+                format!("The {} was a {}.", unknown_name, item.name()),
+                EventType::Item,
+            ));
+        }
+
+        if self.player.satiation > p_satiation {
+            self.events.push(Event::new(
+                format!("You eat the {}.", item.name().to_lowercase()),
+                EventType::Item,
+            ));
+        }
+
+        if self.player.hp > p_hp {
+            self.events.push(Event::new(
+                format!("Player gained {} HP.", self.player.hp - p_hp),
+                EventType::Healing,
+            ));
+        }
+
+        for effect in self.player.effects.iter() {
+            let refreshed = p_effects
+                .iter()
+                .any(|e| e.kind == effect.kind && e.turns >= effect.turns);
+
+            if !refreshed {
+                self.events.push(Event::new(
+                    String::from(effect.kind.start_message()),
+                    EventType::Status,
+                ));
+            }
+        }
+
+        if !item.is_spent() {
+            self.player.return_item(index, item);
+        }
+
+        if self.player.pos != p_pos {
+            self.map.update_fov(self.player.pos, SIGHT_RADIUS);
+            self.pick_up_items();
+        }
+
+        self.remove_dead_monsters();
+        self.end_turn();
+    }
+
+    pub fn camera(&self) -> Coords {
+        Coords::new(
+            clamp(
+                self.player.pos.x - DISPLAY_MAP_WIDTH / 2,
+                0,
+                DISPLAY_MAP_WIDTH * 2,
+            ),
+            clamp(
+                self.player.pos.y - DISPLAY_MAP_HEIGHT / 2,
+                0,
+                DISPLAY_MAP_HEIGHT * 2,
+            ),
+        )
+    }
+
+    pub fn describe(&self, pos: Coords) -> String {
+        if !self.map.is_explored(pos) {
+            return String::from("Unexplored");
+        }
+
+        let mut parts = Vec::new();
+
+        if let Some(tile) = self.map.tile_at(pos) {
+            parts.push(String::from(tile.tile_type.description()));
+        }
+
+        if self.player.pos == pos {
+            parts.push(format!("You (HP: {})", self.player.hp));
+        }
+
+        if let Some(monster) = self.map.monster_at(pos).filter(|_| self.map.is_visible(pos)) {
+            parts.push(format!("{} (HP: {})", monster.name(), monster.hp()));
+        }
+
+        if self.map.is_shopkeeper(pos) && self.map.is_visible(pos) {
+            parts.push(String::from("Shopkeeper"));
+        }
+
+        for item in self.map.items_at(pos) {
+            parts.push(self.identification.name(&**item));
+        }
+
+        for trap in self.map.traps.iter().filter(|t| t.pos == pos && !t.hidden) {
+            parts.push(String::from(trap.kind.name()));
+        }
+
+        parts.join(", ")
+    }
+
+    fn draw_look(&self, ts: &mut TileSet, cursor: Coords) {
+        let camera = self.camera();
+
+        ts.queue_tile(
+            "solid",
+            (cursor.x - camera.x, cursor.y - camera.y),
+            Some(Color::from_rgba(255, 255, 0, 127)),
+        ).unwrap();
+
+        let description = self.describe(cursor);
+        ts.queue_string(&format!("Look: {}", description), (0, 1), None, None);
+    }
+
+    /// Moves `pos` by `dx`, `dy`, keeping it on screen.
+    fn step_cursor(&self, pos: Coords, dx: i32, dy: i32) -> Coords {
+        let camera = self.camera();
+
+        Coords::new(
+            clamp(
+                pos.x + dx,
+                camera.x,
+                clamp(camera.x + DISPLAY_MAP_WIDTH - 1, 0, MAP_WIDTH - 1),
+            ),
+            clamp(
+                pos.y + dy,
+                camera.y,
+                clamp(camera.y + DISPLAY_MAP_HEIGHT - 1, 0, MAP_HEIGHT - 1),
+            ),
+        )
+    }
+
+    pub fn move_look_cursor(&mut self, dx: i32, dy: i32) {
+        if let Some(cursor) = self.look_cursor {
+            self.look_cursor = Some(self.step_cursor(cursor, dx, dy));
+        }
+    }
+
+    pub fn visible_monsters(&self) -> Vec<Coords> {
+        let player_position = self.player.pos;
+        let mut visible: Vec<_> = self.map
+            .monsters
+            .iter()
+            .map(|m| m.pos())
+            .filter(|&pos| self.map.can_see(player_position, pos, SIGHT_RADIUS))
+            .collect();
+
+        visible.sort_by_key(|pos| {
+            let (dx, dy) = (pos.x - player_position.x, pos.y - player_position.y);
+            dx * dx + dy * dy
+        });
+
+        visible
+    }
+
+    /// Starts picking a tile to aim at, with the cursor on the nearest
+    /// monster.
+    pub fn start_targeting(&mut self, action: TargetAction) {
+        if let TargetAction::Fire = action {
+            if !self.player
+                .inv
+                .iter()
+                .any(|s| s.item().thrown_damage().is_some())
+            {
+                self.events.push(Event::new(
+                    String::from("Nothing to throw."),
+                    EventType::Item,
+                ));
+                return;
+            }
+        }
+
+        let targets = self.visible_monsters();
+        self.targeting = Some(Targeting::new(targets, self.player.pos, action));
+    }
+
+    fn projectile_path(&self, target: Coords) -> Vec<Coords> {
+        self.map.projectile_path(self.player.pos, target)
+    }
+
+    fn fire(&mut self) {
+        let (target, action) = match self.targeting {
+            Some(ref targeting) => (targeting.target(), targeting.action),
+            None => return,
+        };
+
+        if target == self.player.pos {
+            self.events.push(Event::new(
+                String::from("Pick a target first."),
+                EventType::Info,
+            ));
+            return;
+        }
+
+        self.targeting = None;
+
+        match action {
+            TargetAction::Fire => {
+                let index = self.player
+                    .inv
+                    .iter()
+                    .position(|s| s.item().thrown_damage().is_some());
+
+                if let Some(index) = index {
+                    self.throw_item(index, target);
+                }
+            }
+            TargetAction::Throw(index) => self.throw_item(index, target),
+            TargetAction::Zap(index) => self.apply_item(index, Some(target)),
+        }
+    }
+
+    /// Throws the item in inventory slot `index` towards `target`. It hits
+    /// the first monster in its way, or lands at the end of its flight.
+    fn throw_item(&mut self, index: usize, target: Coords) {
+        let item = match self.player.take_item(index) {
+            Some(item) => item,
+            None => return,
+        };
+        let name = self.identification.name(&*item);
+        let path = self.projectile_path(target);
+        let landing = *path.last().unwrap_or(&self.player.pos);
+
+        if item.shatters() {
+            self.shatter(item, landing);
+        } else if let Some(pos) = self.map.monsters.iter().position(|m| m.pos() == landing) {
+            let damage = item.thrown_damage().unwrap_or(0);
+            let monster = self.map.monsters.get_mut(pos).unwrap();
+            let m_prev_hp = monster.hp();
+
+            monster.take_damage(damage);
+
+            let msg = if damage > 0 {
+                format!("Monster took {} damage.", m_prev_hp - monster.hp())
+            } else {
+                format!("The {} bounces off the {}.", name, monster.name())
+            };
+
+            self.events.push(Event::new(msg, EventType::Combat));
+            self.map.items.push((landing, item));
+        } else {
+            if item.thrown_damage().is_some() {
+                self.events.push(Event::new(
+                    format!("{} missed.", item.name()),
+                    EventType::Combat,
+                ));
+            }
+
+            self.map.items.push((landing, item));
+        }
+
+        self.remove_dead_monsters();
+        self.end_turn();
+    }
+
+    /// Breaks a thrown `item` at `pos`, splashing every creature next to it.
+    fn shatter(&mut self, item: Box<Item>, pos: Coords) {
+        let near = |c: Coords| (c.x - pos.x).abs() <= 1 && (c.y - pos.y).abs() <= 1;
+
+        self.events.push(Event::new(
+            format!("The {} shatters!", self.identification.name(&*item)),
+            EventType::Item,
+        ));
+
+        for monster in self.map.monsters.iter_mut().filter(|m| near(m.pos())) {
+            item.splash(&mut **monster);
+            self.events.push(Event::new(
+                format!("The {} is caught in the splash.", monster.name()),
+                EventType::Item,
+            ));
+        }
+
+        if near(self.player.pos) {
+            item.splash(&mut self.player);
+            self.events.push(Event::new(
+                String::from("You are caught in the splash."),
+                EventType::Item,
+            ));
+            self.check_player_death(String::from("Caught in a splash"));
+        }
+    }
+
+    /// Buys the item in slot `index` of the shop's stock. Returns whether the
+    /// player could afford it.
+    pub fn buy_item(&mut self, index: usize) -> bool {
+        let price = match self.map.shop {
+            Some(ref shop) => match shop.stock.get(index) {
+                Some(item) => shop.price(&**item),
+                None => return false,
+            },
+            None => return false,
+        };
+
+        if price > self.player.gold {
+            self.events.push(Event::new(
+                String::from("You can't afford that."),
+                EventType::Info,
+            ));
+            return false;
+        }
+
+        let item = self.map.shop.as_mut().unwrap().stock.remove(index);
+        self.player.gold -= price;
+        self.events.push(Event::new(
+            format!(
+                "You buy the {} for {} gold.",
+                self.identification.name(&*item),
+                price
+            ),
+            EventType::Item,
+        ));
+        self.player.add_item(item);
+
+        true
+    }
+
+    /// Sells one item off the stack in inventory slot `index` to the shop.
+    /// Returns whether the shopkeeper took it.
+    pub fn sell_item(&mut self, index: usize) -> bool {
+        let offer = match (&self.map.shop, self.player.inv.get(index)) {
+            (Some(shop), Some(stack)) => shop.offer(stack.item()),
+            _ => return false,
+        };
+
+        if offer == 0 {
+            self.events.push(Event::new(
+                String::from("The shopkeeper isn't interested."),
+                EventType::Info,
+            ));
+            return false;
+        }
+
+        let item = match self.player.take_item(index) {
+            Some(item) => item,
+            None => return false,
+        };
+
+        self.player.gold += offer;
+        self.events.push(Event::new(
+            format!(
+                "You sell the {} for {} gold.",
+                self.identification.name(&*item),
+                offer
+            ),
+            EventType::Item,
+        ));
+
+        if let Some(ref mut shop) = self.map.shop {
+            shop.stock.push(item);
+        }
+
+        true
+    }
+
+    /// Drops the item in inventory slot `index` where the player stands.
+    pub fn drop_item(&mut self, index: usize) -> bool {
+        let item = match self.player.take_item(index) {
+            Some(item) => item,
+            None => return false,
+        };
+
+        self.events.push(Event::new(
+            format!("Dropped {}.", self.identification.name(&*item)),
+            EventType::Item,
+        ));
+        self.map.items.push((self.player.pos, item));
+        self.end_turn();
+
+        true
+    }
+
+    fn draw_targeting(&self, ts: &mut TileSet) {
+        let camera = self.camera();
+        let target = match self.targeting {
+            Some(ref targeting) => targeting.target(),
+            None => return,
+        };
+
+        for point in self.projectile_path(target) {
+            ts.queue_tile(
+                "solid",
+                (point.x - camera.x, point.y - camera.y),
+                Some(Color::from_rgba(255, 0, 0, 95)),
+            ).unwrap();
+        }
+
+        ts.queue_tile(
+            "solid",
+            (target.x - camera.x, target.y - camera.y),
+            Some(Color::from_rgba(255, 0, 0, 159)),
+        ).unwrap();
+
+        let description = self.describe(target);
+        ts.queue_string(
+            &format!("Target: {} [Tab] next [F] fire", description),
+            (0, 1),
+            None,
+            None,
+        );
+    }
+
+    pub fn targeting_key_down(&mut self, keycode: event::Keycode) {
+        use event::Keycode::*;
+
+        let direction = match keycode {
+            Left => Some((-1, 0)),
+            Right => Some((1, 0)),
+            Up => Some((0, -1)),
+            Down => Some((0, 1)),
+            _ => None,
+        };
+
+        if let Some((dx, dy)) = direction {
+            let cursor = match self.targeting {
+                Some(ref targeting) => self.step_cursor(targeting.cursor, dx, dy),
+                None => return,
+            };
+
+            if let Some(ref mut targeting) = self.targeting {
+                targeting.cursor = cursor;
+            }
+
+            return;
+        }
+
+        match keycode {
+            Tab => {
+                if let Some(ref mut targeting) = self.targeting {
+                    targeting.next();
+                }
+            }
+            Backspace => {
+                if let Some(ref mut targeting) = self.targeting {
+                    targeting.previous();
+                }
+            }
+            F | Return => self.fire(),
+            Escape => self.targeting = None,
+            _ => {}
+        }
+    }
+
+    fn kill_monster(&mut self, index: usize) {
+        let monster = self.map.monsters.remove(index);
+        *self.kills.entry(monster.name()).or_insert(0) += 1;
+        self.events
+            .push(Event::new(String::from("Monster died."), EventType::Combat));
+
+        let mut rng = thread_rng();
+
+        if let Some(corpse) = monster.corpse().filter(|_| rng.gen()) {
+            self.map.add_item(monster.pos(), corpse);
+        }
+
+        if let Some(item) = loot::roll(monster.drops(), &self.difficulty(), &mut rng) {
+            self.map.items.push((monster.pos(), item));
+        }
+
+        let xp = monster.xp_value();
+
+        if xp > 0 && self.player.gain_xp(xp) > 0 {
+            self.events.push(Event::new(
+                format!("Welcome to level {}!", self.player.level),
+                EventType::Info,
+            ));
+        }
+    }
+
+    /// Moves the player, attacking or opening a door if one is in the way.
+    /// Returns whether that used up the player's turn.
+    pub fn move_player(&mut self, dx: i32, dy: i32) -> bool {
+        let mut rng = thread_rng();
+        let (dx, dy) = if self.player.has_effect(StatusKind::Confusion) && rng.gen() {
+            *rng.choose(&DIRECTIONS).unwrap()
+        } else {
+            (dx, dy)
+        };
+
+        let target = (self.player.pos.x + dx, self.player.pos.y + dy);
+
+        if self.map.is_shopkeeper(target.into()) {
+            self.activity = None;
+            self.shopping = true;
+            return false;
+        }
+
+        if let Some(tile) = self.map.tile_at(target) {
+            if let Some(pos) = self.map
+                .monsters
+                .iter_mut()
+                .position(|m| m.pos() == tile.pos)
+            {
+                let dead = {
+                    let monster = self.map.monsters.get_mut(pos).unwrap();
+
+                    let m_prev_hp = monster.hp();
+
+                    self.player.deal_damage(&mut **monster);
+
+                    self.events.push(Event::new(
+                        format!("Monster took {} damage.", m_prev_hp - monster.hp()),
+                        EventType::Combat,
+                    ));
+                    monster.is_dead()
+                };
+
+                if dead {
+                    self.kill_monster(pos);
+                }
+
+                return true;
+            } else if tile.tile_type == (TileType::Door { open: false }) {
+                if let Some(door) = self.map.tile_at_mut(target) {
+                    door.tile_type = TileType::Door { open: true };
+                }
+
+                return true;
+            } else if tile.tile_type.is_walkable_tile() {
+                let Coords { x: p_x, y: p_y } = self.player.pos;
+                let entering_shop = self.map.shop.as_ref().is_some_and(|s| {
+                    !s.room.contains((p_x, p_y)) && s.room.contains(target)
+                });
+
+                self.player.pos.x += dx;
+                self.player.pos.y += dy;
+                self.map.update_fov(self.player.pos, SIGHT_RADIUS);
+
+                if entering_shop {
+                    self.events.push(Event::new(
+                        String::from("You enter a shop. Bump into the shopkeeper to trade."),
+                        EventType::Info,
+                    ));
+                }
+
+                self.pick_up_items();
+                self.trigger_trap();
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Ends the player's turn. Game time then moves on, letting monsters act,
+    /// until the player has built up enough energy to act again.
+    pub fn end_turn(&mut self) {
+        self.player.energy -= ACTION_COST;
+
+        while self.player.energy < ACTION_COST && self.cause_of_death.is_none() {
+            self.turn += 1;
+            self.player.energy += self.player.speed();
+            self.player.regenerate(self.turn);
+            self.digest();
+            self.tick_effects();
+
+            for monster in self.map.monsters.iter_mut() {
+                let energy = monster.energy() + monster.speed();
+                monster.set_energy(energy);
+            }
+
+            self.monster_turns();
+
+            if self.turn.is_multiple_of(self.difficulty().wanderer_interval) {
+                self.spawn_wanderer();
+            }
+        }
+
+        self.map.update_fov(self.player.pos, SIGHT_RADIUS);
+    }
+
+    /// Brings in a wandering monster somewhere out of the player's sight.
+    fn spawn_wanderer(&mut self) {
+        let mut rng = thread_rng();
+        let difficulty = self.difficulty();
+        let spawn = spawns::roll(spawns::WANDERING, &difficulty, &mut rng);
+
+        for _ in 0..20 {
+            let pos = match self.map.random_walkable_tile(self.player.pos, &mut rng) {
+                Some(pos) => pos,
+                None => return,
+            };
+
+            let in_shop = self.map.shop.as_ref().is_some_and(|s| s.room.contains((pos.x, pos.y)));
+
+            if self.map.is_visible(pos) || in_shop {
+                continue;
+            }
+
+            let monsters = self.map.monsters.len();
+            let player = self.player.pos;
+            spawns::place_near(&mut self.map, pos, player, spawn, &difficulty, &mut rng);
+
+            if self.map.monsters.len() > monsters {
+                self.events.push(Event::new(
+                    String::from("You hear something moving in the distance."),
+                    EventType::Info,
+                ));
+            }
+
+            return;
+        }
+    }
+
+    /// Makes the player a turn hungrier, with whatever that brings.
+    fn digest(&mut self) {
+        if let Some(hunger) = self.player.digest() {
+            self.activity = None;
+            self.events.push(Event::new(
+                String::from(hunger.message()),
+                EventType::Status,
+            ));
+        }
+
+        match self.player.hunger() {
+            Hunger::Fainting if thread_rng().gen_range(0, FAINT_CHANCE) == 0 => {
+                self.activity = None;
+                self.player.energy -= ACTION_COST * FAINT_TURNS;
+                self.events.push(Event::new(
+                    String::from("You faint from hunger."),
+                    EventType::Status,
+                ));
+            }
+            Hunger::Starved => {
+                self.player.hp = 0;
+                self.check_player_death(String::from("Starved to death"));
+            }
+            _ => {}
+        }
+    }
+
+    /// Lets the status effects on the player and every monster run for a turn.
+    fn tick_effects(&mut self) {
+        for kind in self.player.tick_effects() {
+            self.events.push(Event::new(
+                String::from(kind.end_message()),
+                EventType::Status,
+            ));
+        }
+
+        self.check_player_death(String::from("Succumbed to poison"));
+
+        for monster in self.map.monsters.iter_mut() {
+            monster.tick_effects();
+        }
+
+        self.remove_dead_monsters();
+    }
+
+    fn remove_dead_monsters(&mut self) {
+        while let Some(index) = self.map.monsters.iter().position(|m| m.is_dead()) {
+            self.kill_monster(index);
+        }
+    }
+
+    pub fn start_resting(&mut self) {
+        if self.player.hp >= self.player.max_hp {
+            self.events.push(Event::new(
+                String::from("Already at full HP."),
+                EventType::Info,
+            ));
+        } else if self.player.hunger() >= Hunger::Weak {
+            self.events.push(Event::new(
+                String::from("Too hungry to rest."),
+                EventType::Info,
+            ));
+        } else if !self.visible_monsters().is_empty() {
+            self.events.push(Event::new(
+                String::from("Not with monsters in view."),
+                EventType::Info,
+            ));
+        } else {
+            self.activity = Some(Activity::Rest);
+        }
+    }
+
+    fn rest_step(&mut self) -> bool {
+        if self.player.hp >= self.player.max_hp {
+            self.events.push(Event::new(
+                String::from("HP fully restored."),
+                EventType::Healing,
+            ));
+            return false;
+        }
+
+        // Weak players don't heal, so resting would only burn food.
+        if self.player.hunger() >= Hunger::Weak {
+            return false;
+        }
+
+        self.visible_monsters().is_empty()
+    }
+
+    pub fn start_exploring(&mut self) {
+        if self.visible_monsters().is_empty() {
+            self.activity = Some(Activity::Explore);
+        } else {
+            self.events.push(Event::new(
+                String::from("Not with monsters in view."),
+                EventType::Info,
+            ));
+        }
+    }
+
+    /// Takes one step towards the nearest unexplored tile, or closed door.
+    fn explore_step(&mut self) -> bool {
+        let goals: Vec<_> = self.map
+            .iter()
+            .filter(|t| {
+                if t.tile_type == (TileType::Door { open: false }) {
+                    self.map.is_explored(t.pos)
+                } else {
+                    t.tile_type.is_walkable_tile() && !self.map.is_explored(t.pos)
+                }
+            })
+            .map(|t| t.pos)
+            .collect();
+
+        // Only explored tiles are walked over, so the route doesn't give away
+        // what's left to explore, and known traps are walked around.
+        let step = {
+            let (map, start) = (&self.map, self.player.pos);
+            let explore_map = DijkstraMap::with_walkable(map, &goals, |c| {
+                c == start || map.is_explored(c) && !map.is_known_trap(c)
+                    && map.tile_at(c).is_some_and(|t| t.tile_type.is_walkable_tile())
+            });
+
+            explore_map.step_from(start, |c| {
+                map.monster_at(c).is_some() || map.is_shopkeeper(c) || map.is_known_trap(c)
+            })
+        };
+
+        match step {
+            Some(step) => self.move_player(step.x - self.player.pos.x, step.y - self.player.pos.y),
+            None => {
+                self.events.push(Event::new(
+                    String::from("Nothing left to explore."),
+                    EventType::Info,
+                ));
+                false
+            }
+        }
+    }
+
+    pub fn travel_to(&mut self, target: Coords) {
+        if target == self.player.pos {
+            return;
+        }
+
+        let path = {
+            let map = &self.map;
+            let start = (self.player.pos.x, self.player.pos.y);
+
+            PathGrid::new(map.width(), map.height()).ortho_star(
+                start,
+                (target.x, target.y),
+                |x, y| {
+                    let coords = Coords::new(x, y);
+
+                    if (map.is_shopkeeper(coords) || map.is_known_trap(coords))
+                        && coords != target
+                    {
+                        return false;
+                    }
+
+                    map.is_explored(coords) && match map.tile_at(coords) {
+                        Some(tile) => {
+                            tile.tile_type.is_walkable_tile()
+                                || tile.tile_type == TileType::Door { open: false }
+                        }
+                        None => false,
+                    }
+                },
+                |_, _| 1,
+            )
+        };
+
+        match path {
+            Some(path) => {
+                let mut path: Vec<_> = path.into_iter().skip(1).map(Coords::from).collect();
+                path.reverse();
+
+                self.activity = Some(Activity::Travel(path));
+                self.last_step = Instant::now();
+            }
+            None => self.events.push(Event::new(
+                String::from("Can't find a way there."),
+                EventType::Info,
+            )),
+        }
+    }
+
+    fn travel_step(&mut self) -> bool {
+        let (next, arriving) = match self.activity {
+            Some(Activity::Travel(ref path)) => (path.last().cloned(), path.len() == 1),
+            _ => (None, false),
+        };
+
+        let next = match next {
+            Some(next) => next,
+            None => return false,
+        };
+
+        let (dx, dy) = (next.x - self.player.pos.x, next.y - self.player.pos.y);
+
+        // Travelling to the shopkeeper ends in bumping into them, to trade.
+        if dx.abs() + dy.abs() != 1 || self.map.monster_at(next).is_some()
+            || (self.map.is_shopkeeper(next) && !arriving)
+        {
+            return false;
+        }
+
+        let acted = self.move_player(dx, dy);
+
+        // Bumping a closed door only opens it, so the step is retried next turn.
+        if self.player.pos == next {
+            if let Some(Activity::Travel(ref mut path)) = self.activity {
+                path.pop();
+            }
+        }
+
+        acted
+    }
+
+    pub fn start_run(&mut self, dx: i32, dy: i32) {
+        let start = self.player.pos;
+
+        if self.move_player(dx, dy) {
+            self.end_turn();
+
+            if self.player.pos != start {
+                self.activity = Some(Activity::Run(dx, dy));
+                self.last_step = Instant::now();
+            }
+        }
+    }
+
+    /// Takes one more step in the running direction, following bends in
+    /// corridors. Stops at anything worth a look: branches, doors, items and
+    /// monsters.
+    fn run_step(&mut self) -> bool {
+        let (dx, dy) = match self.activity {
+            Some(Activity::Run(dx, dy)) => (dx, dy),
+            _ => return false,
+        };
+
+        if !self.visible_monsters().is_empty() {
+            return false;
+        }
+
+        let Coords { x: p_x, y: p_y } = self.player.pos;
+        let exits: Vec<_> = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .iter()
+            .cloned()
+            .filter(|&d| d != (-dx, -dy))
+            .collect();
+
+        let mut open_exits = Vec::new();
+
+        for &(ex, ey) in exits.iter() {
+            let pos = Coords::new(p_x + ex, p_y + ey);
+
+            if self.map.items_at(pos).next().is_some() {
+                return false;
+            }
+
+            if let Some(tile) = self.map.tile_at(pos) {
+                if let TileType::Door { .. } = tile.tile_type {
+                    return false;
+                }
+
+                if tile.tile_type.is_walkable_tile() {
+                    open_exits.push((ex, ey));
+                }
+            }
+        }
+
+        let in_corridor = self.map
+            .tile_at(self.player.pos)
+            .is_some_and(|t| t.tile_type == TileType::Pathway);
+
+        let (dx, dy) = if in_corridor {
+            if open_exits.len() != 1 {
+                return false;
+            }
+
+            open_exits[0]
+        } else if open_exits.contains(&(dx, dy)) {
+            (dx, dy)
+        } else {
+            return false;
+        };
+
+        if self.map.monster_at(Coords::new(p_x + dx, p_y + dy)).is_some() {
+            return false;
+        }
+
+        self.activity = Some(Activity::Run(dx, dy));
+        self.move_player(dx, dy)
+    }
+
+    /// Advances the current activity by one turn, stopping it once it's done or
+    /// when something needs the player's attention.
+    pub fn continue_activity(&mut self) {
+        let hp = self.player.hp;
+        let item_count = self.player.item_count();
+        let gold = self.player.gold;
+        let monsters_in_view = self.visible_monsters().len();
+
+        let acted = match self.activity {
+            Some(Activity::Explore) => self.explore_step(),
+            Some(Activity::Travel(_)) => self.travel_step(),
+            Some(Activity::Run(..)) => self.run_step(),
+            Some(Activity::Rest) => self.rest_step(),
+            None => false,
+        };
+
+        if !acted {
+            self.activity = None;
+            return;
+        }
+
+        self.end_turn();
+
+        if self.player.hp < hp || self.player.item_count() > item_count
+            || self.player.gold > gold || self.visible_monsters().len() > monsters_in_view
+        {
+            self.activity = None;
+        }
+    }
+
+    /// Lets every monster with enough energy act.
+    fn monster_turns(&mut self) {
+        let approach = DijkstraMap::new(&self.map, &[self.player.pos]);
+        let mut safety = None;
+
+        for index in 0..self.map.monsters.len() {
+            while self.map.monsters[index].energy() >= ACTION_COST {
+                let energy = self.map.monsters[index].energy() - ACTION_COST;
+                self.map.monsters[index].set_energy(energy);
+
+                self.monster_act(index, &approach, &mut safety);
+            }
+        }
+    }
+
+    /// A monster that can see the player, or was alerted by an alarm, attacks
+    /// when adjacent, otherwise it closes in, or runs away when badly hurt.
+    fn monster_act(
+        &mut self,
+        index: usize,
+        approach: &DijkstraMap,
+        safety: &mut Option<DijkstraMap>,
+    ) {
+        let player_position = self.player.pos;
+        let (pos, fleeing) = {
+            let monster = &self.map.monsters[index];
+            (monster.pos(), monster.wants_to_flee())
+        };
+
+        let alerted = self.turn < self.alerted_until;
+
+        if !alerted && !self.map.can_see(pos, player_position, SIGHT_RADIUS) {
+            return;
+        }
+
+        let adjacent = (pos.x - player_position.x).abs() + (pos.y - player_position.y).abs() == 1;
+
+        let mut rng = thread_rng();
+
+        if self.map.monsters[index].has_effect(StatusKind::Confusion) && rng.gen() {
+            let (dx, dy) = *rng.choose(&DIRECTIONS).unwrap();
+            let step = Coords::new(pos.x + dx, pos.y + dy);
+            let free = step != player_position && self.map.monster_at(step).is_none()
+                && !self.map.is_shopkeeper(step)
+                && self.map
+                    .tile_at(step)
+                    .is_some_and(|t| t.tile_type.is_walkable_tile());
+
+            if free {
+                self.map.monsters[index].set_pos(step);
+            }
+
+            return;
+        }
+
+        if adjacent && !fleeing {
+            let p_prev_hp = self.player.hp;
+            let name = self.map.monsters[index].name();
+
+            self.map.monsters[index].deal_damage(&mut self.player);
+
+            self.events.push(Event::new(
+                format!("Player took {} damage.", p_prev_hp - self.player.hp),
+                EventType::Combat,
+            ));
+
+            if let Some(effect) = self.map.monsters[index].attack_effect() {
+                if !self.player.has_effect(effect.kind) {
+                    self.events.push(Event::new(
+                        String::from(effect.kind.start_message()),
+                        EventType::Status,
+                    ));
+                }
+
+                self.player.add_effect(effect);
+            }
+
+            self.check_player_death(format!("Killed by a {}", name));
+            return;
+        }
+
+        let step = {
+            let flow = if fleeing {
+                &*safety.get_or_insert_with(|| approach.inverted())
+            } else {
+                approach
+            };
+            let map = &self.map;
+
+            flow.step_from(pos, |c| {
+                c == player_position || map.monster_at(c).is_some() || map.is_shopkeeper(c)
+            })
+        };
+
+        if let Some(step) = step {
+            self.map.monsters[index].set_pos(step);
+        }
+    }
+
+    fn pick_up_items(&mut self) {
+        let pos = self.player.pos;
+
+        let mut picked_up: Vec<(String, usize)> = Vec::new();
+
+        while let Some(pos) = self.map.items.iter().position(|(p, _)| p == &pos) {
+            let item = self.map.items.remove(pos).1;
+            let name = self.identification.name(&*item);
+
+            if let Some(amount) = item.gold() {
+                self.player.gold += amount;
+                self.events.push(Event::new(
+                    format!("Picked up {}.", name),
+                    EventType::Item,
+                ));
+                continue;
+            }
+
+            match picked_up.iter_mut().find(|(n, _)| *n == name) {
+                Some(entry) => entry.1 += 1,
+                None => picked_up.push((name, 1)),
+            }
+
+            self.player.add_item(item);
+        }
+
+        for (name, count) in picked_up {
+            self.events.push(Event::new(
+                format!("Picked up {}.", identification::counted(&name, count)),
+                EventType::Item,
+            ));
+        }
+    }
+
+    fn trigger_trap(&mut self) {
+        let kind = match self.map.trap_at_mut(self.player.pos) {
+            Some(trap) => {
+                trap.hidden = false;
+                trap.kind
+            }
+            None => return,
+        };
+
+        match kind {
+            TrapKind::Dart => {
+                self.player.take_damage(5);
+                self.events.push(Event::new(
+                    String::from("A dart shoots out! Player took 5 damage."),
+                    EventType::Trap,
+                ));
+                self.check_player_death(format!("Killed by a {}", kind.name()));
+            }
+            TrapKind::Teleport => {
+                let from = self.player.pos;
+
+                if let Some(pos) = self.map.random_walkable_tile(from, &mut thread_rng()) {
+                    self.player.pos = pos;
+                    self.map.update_fov(pos, SIGHT_RADIUS);
+                }
+
+                self.events.push(Event::new(
+                    String::from("A teleport trap whisks you away!"),
+                    EventType::Trap,
+                ));
+                self.pick_up_items();
+            }
+            TrapKind::Alarm => {
+                self.alerted_until = self.turn + ALARM_TURNS;
+                self.events.push(Event::new(
+                    String::from("An alarm blares through the dungeon!"),
+                    EventType::Trap,
+                ));
+            }
+        }
+    }
+
+    pub fn search(&mut self) {
+        let Coords { x: p_x, y: p_y } = self.player.pos;
+        let mut rng = thread_rng();
+        let mut found = Vec::new();
+
+        for trap in self.map.traps.iter_mut().filter(|t| {
+            t.hidden && (t.pos.x - p_x).abs() <= 1 && (t.pos.y - p_y).abs() <= 1
+        }) {
+            if rng.gen_range(0, 3) != 0 {
+                trap.hidden = false;
+                found.push(trap.kind);
+            }
+        }
+
+        if found.is_empty() {
+            self.events
+                .push(Event::new(String::from("Found nothing."), EventType::Info));
+        }
+
+        for kind in found {
+            self.events.push(Event::new(
+                format!("Found a {}.", kind.name()),
+                EventType::Trap,
+            ));
+        }
+    }
+
+    pub fn close_door(&mut self) -> bool {
+        let Coords { x: p_x, y: p_y } = self.player.pos;
+
+        for &(x, y) in [(p_x - 1, p_y), (p_x + 1, p_y), (p_x, p_y - 1), (p_x, p_y + 1)].iter() {
+            let pos = Coords::new(x, y);
+            let blocked =
+                self.map.monster_at(pos).is_some() || self.map.items_at(pos).next().is_some();
+
+            if let Some(tile) = self.map.tile_at_mut(pos) {
+                if tile.tile_type == (TileType::Door { open: true }) && !blocked {
+                    tile.tile_type = TileType::Door { open: false };
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    fn draw_events(&mut self, ts: &mut TileSet) {
+        let time_to_expire = Duration::from_millis(5000);
+        self.events
+            .iter_mut()
+            .filter(|e| !e.disabled && e.time.elapsed() > time_to_expire)
+            .for_each(|e| e.disabled = true);
+
+        let get_transparency = |e: &Event| -> u8 {
+            let elapsed =
+                e.time.elapsed().as_secs() as u32 * 1000 + e.time.elapsed().subsec_millis();
+            let elapsed = elapsed as f64;
+            if elapsed > 3000f64 {
+                255 - (255f64 * ((elapsed - 3000f64) / 2000f64)) as u8
+            } else {
+                255
+            }
+        };
+
+        let events: Vec<_> = self.events
+            .iter()
+            .rev()
+            .filter(|e| !e.disabled)
+            .take(5)
+            .map(|s| s.clone())
+            .collect();
+        let len = events.len() as i32;
+        for (i, event) in events.into_iter().enumerate() {
+            let t = get_transparency(&event);
+            let (r, g, b) = event.ty.color().to_rgb();
+            let altered_color: Color = (r, g, b, t).into();
+
+            ts.queue_string(
+                "[",
+                (0, DISPLAY_MAP_HEIGHT - (len - i as i32)),
+                Some((0, 0, 0, t).into()),
+                Some((255, 255, 255, t).into()),
+            );
+            ts.queue_tile_with_background(
+                "solid",
+                event.ty.icon(),
+                (1, DISPLAY_MAP_HEIGHT - (len - i as i32)),
+                Some((0, 0, 0, t).into()),
+                Some(altered_color),
+            ).unwrap();
+            ts.queue_string(
+                "]",
+                (2, DISPLAY_MAP_HEIGHT - (len - i as i32)),
+                Some((0, 0, 0, t).into()),
+                Some((255, 255, 255, t).into()),
+            );
+
+            ts.queue_string(
+                &event.msg,
+                (3, DISPLAY_MAP_HEIGHT - (len - i as i32)),
+                Some((0, 0, 0, t).into()),
+                Some((255, 255, 255, t).into()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entities;
+    use helpers::Rect;
+    use map::{Shop, Tile, Trap};
+
+    /// Puts the player at `player` on a fully explored level drawn with `#` for
+    /// walls, `+` for closed doors, `^` for known traps and `.` for floor.
+    fn game_on(rows: &[&str], player: Coords) -> Game {
+        let mut game = Game::new();
+        let mut map = Map::new(rows[0].len() as i32, rows.len() as i32);
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let pos = Coords::new(x as i32, y as i32);
+                let tile_type = match c {
+                    '#' => TileType::Wall,
+                    '+' => TileType::Door { open: false },
+                    _ => TileType::BlankRoomFloor,
+                };
+
+                if c == '^' {
+                    map.traps.push(Trap {
+                        hidden: false,
+                        ..Trap::new(pos, TrapKind::Dart)
+                    });
+                }
+
+                *map.tile_at_mut(pos).unwrap() = Tile {
+                    pos,
+                    tile_type,
+                    color: None,
+                };
+            }
+        }
+
+        map.reveal();
+        game.map = map;
+        game.player.pos = player;
+        game
+    }
+
+    #[test]
+    fn explore_steps_around_known_traps() {
+        let mut game = game_on(&[".^..+", "....#"], Coords::new(0, 0));
+
+        assert!(game.explore_step());
+        assert_eq!(game.player.pos, Coords::new(0, 1));
+    }
+
+    #[test]
+    fn wanderers_keep_off_the_player() {
+        let player = Coords::new(1, 1);
+        let mut game = game_on(&["...", "...", "..."], player);
+        let spawn = spawns::Spawn::Group(spawns::Monster::Goblin, 8, 8);
+
+        let difficulty = game.difficulty();
+
+        spawns::place_near(&mut game.map, player, player, spawn, &difficulty, &mut thread_rng());
+
+        assert_eq!(game.map.monsters.len(), 8);
+        assert!(game.map.monster_at(player).is_none());
+    }
+
+    #[test]
+    fn picking_up_gold_stops_travel() {
+        let mut game = game_on(&["....."], Coords::new(0, 0));
+        game.map.add_item(Coords::new(1, 0), entities::Gold { amount: 10 });
+        game.travel_to(Coords::new(4, 0));
+        game.continue_activity();
+
+        assert_eq!(game.player.gold, 10);
+        assert!(game.activity.is_none());
+    }
+
+    #[test]
+    fn weak_players_cannot_rest() {
+        let mut game = game_on(&["..."], Coords::new(0, 0));
+        game.player.hp = 1;
+        game.player.satiation = 100;
+        game.start_resting();
+
+        assert!(game.activity.is_none());
+    }
+
+    #[test]
+    fn growing_weak_stops_resting() {
+        let mut game = game_on(&["..."], Coords::new(0, 0));
+        game.player.hp = 1;
+        game.player.satiation = 152;
+        game.start_resting();
+
+        while game.activity.is_some() {
+            game.continue_activity();
+        }
+
+        assert_eq!(game.player.hunger(), Hunger::Weak);
+        assert_eq!(game.player.satiation, 150);
+    }
+
+    #[test]
+    fn thrown_items_stop_short_of_the_shopkeeper() {
+        let mut game = game_on(&["....."], Coords::new(0, 0));
+        game.map.shop = Some(Shop {
+            room: Rect::new(0, 0, 5, 1),
+            keeper: Coords::new(3, 0),
+            stock: Vec::new(),
+        });
+        game.player.add_item(Box::new(entities::ThrowingKnife));
+        game.throw_item(game.player.inv.len() - 1, Coords::new(4, 0));
+
+        assert_eq!(game.map.items_at(Coords::new(2, 0)).count(), 1);
+        assert_eq!(game.map.items_at(Coords::new(3, 0)).count(), 0);
+    }
+
+    #[test]
+    fn travel_goes_around_known_traps() {
+        let mut game = game_on(&["..^..", "....."], Coords::new(0, 0));
+        game.travel_to(Coords::new(4, 0));
+
+        match game.activity {
+            Some(Activity::Travel(ref path)) => {
+                assert!(!path.contains(&Coords::new(2, 0)));
+                assert_eq!(path.first(), Some(&Coords::new(4, 0)));
+            }
+            _ => panic!("not travelling"),
+        }
+    }
+
+    #[test]
+    fn travel_can_end_on_a_known_trap() {
+        let mut game = game_on(&["..^..", "....."], Coords::new(0, 0));
+        game.travel_to(Coords::new(2, 0));
+
+        match game.activity {
+            Some(Activity::Travel(ref path)) => assert_eq!(path.len(), 2),
+            _ => panic!("not travelling"),
+        }
+    }
+}
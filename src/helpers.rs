@@ -18,7 +18,6 @@ impl Rect {
         }
     }
 
-    #[allow(dead_code)]
     pub fn contains(&self, (x, y): (i32, i32)) -> bool {
         (x >= self.left() && x <= self.right()) && (y >= self.top() && y <= self.bottom())
     }
@@ -89,6 +88,41 @@ pub fn clamp<T: PartialOrd>(x: T, min: T, max: T) -> T {
     }
 }
 
+/// Returns every point on the Bresenham line from `start` to `end`, including
+/// both endpoints.
+pub fn line(start: Coords, end: Coords) -> Vec<Coords> {
+    let dx = (end.x - start.x).abs();
+    let dy = -(end.y - start.y).abs();
+    let step_x = if start.x < end.x { 1 } else { -1 };
+    let step_y = if start.y < end.y { 1 } else { -1 };
+
+    let mut points = Vec::with_capacity((dx - dy) as usize + 1);
+    let mut current = start;
+    let mut err = dx + dy;
+
+    loop {
+        points.push(current);
+
+        if current == end {
+            break;
+        }
+
+        let err2 = err * 2;
+
+        if err2 >= dy {
+            err += dy;
+            current.x += step_x;
+        }
+
+        if err2 <= dx {
+            err += dx;
+            current.y += step_y;
+        }
+    }
+
+    points
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Coords {
     pub x: i32,
@@ -119,3 +153,56 @@ impl<'a> From<&'a (i32, i32)> for Coords {
         Coords { x, y }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(start: (i32, i32), end: (i32, i32)) -> Vec<(i32, i32)> {
+        line(start.into(), end.into())
+            .into_iter()
+            .map(|c| (c.x, c.y))
+            .collect()
+    }
+
+    #[test]
+    fn line_of_zero_length_is_its_start() {
+        assert_eq!(points((3, 4), (3, 4)), vec![(3, 4)]);
+    }
+
+    #[test]
+    fn line_runs_straight_along_axes_and_diagonals() {
+        assert_eq!(points((0, 0), (3, 0)), vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(points((0, 0), (0, -2)), vec![(0, 0), (0, -1), (0, -2)]);
+        assert_eq!(points((0, 0), (-2, 2)), vec![(0, 0), (-1, 1), (-2, 2)]);
+    }
+
+    #[test]
+    fn line_covers_every_octant() {
+        let ends = [
+            (5, 2),
+            (2, 5),
+            (-2, 5),
+            (-5, 2),
+            (-5, -2),
+            (-2, -5),
+            (2, -5),
+            (5, -2),
+        ];
+
+        for &(x, y) in ends.iter() {
+            let path = points((0, 0), (x, y));
+
+            assert_eq!(path.first(), Some(&(0, 0)));
+            assert_eq!(path.last(), Some(&(x, y)));
+            assert_eq!(path.len() as i32, x.abs().max(y.abs()) + 1);
+
+            for step in path.windows(2) {
+                let ((x1, y1), (x2, y2)) = (step[0], step[1]);
+                assert_eq!((x2 - x1).abs().max((y2 - y1).abs()), 1);
+                // Never steps backwards along either axis.
+                assert!((x2 - x1) * x.signum() >= 0 && (y2 - y1) * y.signum() >= 0);
+            }
+        }
+    }
+}
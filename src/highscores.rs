@@ -0,0 +1,203 @@
+//! The local high score table, kept in a plain-text file next to the game.
+//!
+//! Each line holds one run as tab-separated fields. Lines that don't parse are
+//! skipped, so a damaged file costs at most the entries it mangled.
+
+use game::Game;
+use std::{
+    cmp::Reverse, fs, io::{self, Write},
+};
+
+const HIGH_SCORES_FILE: &str = "highscores.txt";
+/// How many runs the table remembers.
+const MAX_ENTRIES: usize = 10;
+
+const SCORE_PER_DEPTH: u32 = 1000;
+const SCORE_PER_KILL: u32 = 100;
+const SCORE_PER_GOLD: u32 = 1;
+/// One point is taken off for this many turns, to reward quick runs.
+const TURNS_PER_PENALTY: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct Score {
+    pub score: u32,
+    pub depth: i32,
+    pub kills: u32,
+    pub gold: u32,
+    pub turns: u32,
+    pub cause: String,
+}
+
+impl Score {
+    pub fn from_game(game: &Game) -> Score {
+        let depth = game.depth;
+        let kills = game.total_kills();
+        let gold = game.player.gold;
+        let turns = game.turn;
+
+        let score = (depth.max(0) as u32 * SCORE_PER_DEPTH + kills * SCORE_PER_KILL
+            + gold * SCORE_PER_GOLD)
+            .saturating_sub(turns / TURNS_PER_PENALTY);
+
+        Score {
+            score,
+            depth,
+            kills,
+            gold,
+            turns,
+            cause: game.cause_of_death
+                .clone()
+                .unwrap_or_else(|| String::from("Still alive")),
+        }
+    }
+
+    fn parse(line: &str) -> Option<Score> {
+        let mut fields = line.split('\t');
+
+        let score = Score {
+            score: fields.next()?.parse().ok()?,
+            depth: fields.next()?.parse().ok()?,
+            kills: fields.next()?.parse().ok()?,
+            gold: fields.next()?.parse().ok()?,
+            turns: fields.next()?.parse().ok()?,
+            cause: String::from(fields.next()?),
+        };
+
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(score)
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.score,
+            self.depth,
+            self.kills,
+            self.gold,
+            self.turns,
+            self.cause.replace(&['\t', '\n'][..], " ")
+        )
+    }
+}
+
+pub struct HighScores {
+    pub entries: Vec<Score>,
+}
+
+impl HighScores {
+    /// Reads the table from disk. A missing or unreadable file gives an empty
+    /// table.
+    pub fn load() -> HighScores {
+        let contents = match fs::read_to_string(HIGH_SCORES_FILE) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                println!("Couldn't read high scores: {}", e);
+                String::new()
+            }
+        };
+
+        HighScores::parse(&contents)
+    }
+
+    /// Reads the table from the contents of a high score file, skipping lines
+    /// that don't hold a valid entry.
+    fn parse(contents: &str) -> HighScores {
+        let mut entries = Vec::new();
+
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            match Score::parse(line) {
+                Some(score) => entries.push(score),
+                None => println!("Skipping corrupt high score entry: {:?}", line),
+            }
+        }
+
+        entries.sort_by_key(|s| Reverse(s.score));
+        entries.truncate(MAX_ENTRIES);
+
+        HighScores { entries }
+    }
+
+    /// Adds `score` to the table, returning its index if it made the cut.
+    pub fn add(&mut self, score: Score) -> Option<usize> {
+        let index = self.entries
+            .iter()
+            .position(|s| s.score < score.score)
+            .unwrap_or(self.entries.len());
+
+        if index >= MAX_ENTRIES {
+            return None;
+        }
+
+        self.entries.insert(index, score);
+        self.entries.truncate(MAX_ENTRIES);
+
+        Some(index)
+    }
+
+    /// Writes the table to disk. The new contents go to a temporary file first,
+    /// so a crash halfway through can't wipe the old table.
+    pub fn save(&self) -> io::Result<()> {
+        let tmp = format!("{}.tmp", HIGH_SCORES_FILE);
+
+        {
+            let mut file = fs::File::create(&tmp)?;
+
+            for score in self.entries.iter() {
+                writeln!(file, "{}", score.to_line())?;
+            }
+
+            file.sync_all()?;
+        }
+
+        fs::rename(&tmp, HIGH_SCORES_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrupt_lines_are_skipped() {
+        let contents = "\
+            500\t1\t3\t200\t1000\tKilled by a Goblin\n\
+            this is not a score\n\
+            \n\
+            900\t2\t5\t400\t2000\tStarved\n\
+            700\t1\t4\n\
+            lots\t1\t4\t0\t100\tKilled by a dart trap\n\
+            800\t1\t4\t0\t100\tKilled by a dart trap\textra\n\
+            100\t1\t0\t0\t50\tStill alive\n";
+
+        let high_scores = HighScores::parse(contents);
+        let scores: Vec<_> = high_scores.entries.iter().map(|s| s.score).collect();
+
+        assert_eq!(scores, vec![900, 500, 100]);
+        assert_eq!(high_scores.entries[0].cause, "Starved");
+    }
+
+    #[test]
+    fn table_keeps_the_best_entries() {
+        let contents: String = (1..=15)
+            .map(|i| format!("{}\t1\t0\t0\t0\tStill alive\n", i * 10))
+            .collect();
+
+        let mut high_scores = HighScores::parse(&contents);
+        let scores: Vec<_> = high_scores.entries.iter().map(|s| s.score).collect();
+
+        assert_eq!(scores.len(), MAX_ENTRIES);
+        assert_eq!(scores[0], 150);
+        assert_eq!(scores[MAX_ENTRIES - 1], 60);
+
+        let low = Score::parse("5\t1\t0\t0\t0\tStill alive").unwrap();
+        assert_eq!(high_scores.add(low), None);
+
+        let high = Score::parse("1000\t3\t0\t0\t0\tStill alive").unwrap();
+        assert_eq!(high_scores.add(high), Some(0));
+        assert_eq!(high_scores.entries.len(), MAX_ENTRIES);
+    }
+}
@@ -0,0 +1,139 @@
+//! Per-run item appearances. Magic items don't come labelled: each run hands
+//! every kind a random look, and the player only learns which is which by
+//! using them.
+
+use entities::{Item, ItemClass};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+const POTION_APPEARANCES: [&str; 10] = [
+    "murky", "bubbling", "cloudy", "fizzy", "golden", "violet", "smoky", "milky", "oily", "glowing",
+];
+
+const SCROLL_LABELS: [&str; 10] = [
+    "ZELGO MER",
+    "FOOBIE BLETCH",
+    "XIXAXA",
+    "PRATYAVAYAH",
+    "ELBIB YLOH",
+    "VERR YED",
+    "JUYED AWK",
+    "NR 9",
+    "THARR",
+    "KIRJE",
+];
+
+pub struct Identification {
+    /// The appearance of every unidentified item kind, by item id.
+    appearances: HashMap<u32, &'static str>,
+    known: HashSet<u32>,
+}
+
+impl Identification {
+    pub fn new(rng: &mut impl Rng) -> Identification {
+        let mut appearances = HashMap::new();
+
+        for &class in [ItemClass::Potion, ItemClass::Scroll].iter() {
+            let mut pool = match class {
+                ItemClass::Potion => POTION_APPEARANCES.to_vec(),
+                ItemClass::Scroll => SCROLL_LABELS.to_vec(),
+            };
+
+            rng.shuffle(&mut pool);
+
+            for (id, appearance) in class.ids().into_iter().zip(pool) {
+                appearances.insert(id, appearance);
+            }
+        }
+
+        Identification {
+            appearances,
+            known: HashSet::new(),
+        }
+    }
+
+    pub fn is_known(&self, item: &Item) -> bool {
+        item.class().is_none() || self.known.contains(&item.id())
+    }
+
+    /// Marks every item of the same kind as `item` as identified. Returns
+    /// whether it wasn't already.
+    pub fn identify(&mut self, item: &Item) -> bool {
+        !self.is_known(item) && self.known.insert(item.id())
+    }
+
+    /// What the player knows to call `item`.
+    pub fn name(&self, item: &Item) -> String {
+        if let Some(amount) = item.gold() {
+            return format!("{} gold", amount);
+        }
+
+        if self.is_known(item) {
+            return match item.charges() {
+                Some(charges) => format!("{} ({})", item.name(), charges),
+                None => String::from(item.name()),
+            };
+        }
+
+        let appearance = self.appearances.get(&item.id()).cloned().unwrap_or("strange");
+
+        match item.class() {
+            Some(ItemClass::Scroll) => format!("scroll labeled {}", appearance),
+            _ => format!("{} potion", appearance),
+        }
+    }
+}
+
+/// `name` with its count in front when there's more than one, like
+/// "3 murky potions".
+pub fn counted(name: &str, count: usize) -> String {
+    if count == 1 {
+        String::from(name)
+    } else {
+        format!("{} {}", count, plural(name))
+    }
+}
+
+fn plural(name: &str) -> String {
+    // "Potion of haste" and "scroll labeled XIXAXA" pluralize their first word.
+    let (noun, rest) = match name.find(" of ").or_else(|| name.find(" labeled ")) {
+        Some(i) => name.split_at(i),
+        None => (name, ""),
+    };
+
+    let noun = match noun.strip_suffix("fe") {
+        Some(stem) => format!("{}ves", stem),
+        None => format!("{}s", noun),
+    };
+
+    noun + rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_items_are_not_counted() {
+        assert_eq!(counted("Throwing knife", 1), "Throwing knife");
+    }
+
+    #[test]
+    fn plurals_add_an_s() {
+        assert_eq!(counted("murky potion", 2), "2 murky potions");
+        assert_eq!(counted("Ration", 3), "3 Rations");
+        assert_eq!(counted("Goblin corpse", 2), "2 Goblin corpses");
+    }
+
+    #[test]
+    fn plurals_of_knives() {
+        assert_eq!(counted("Throwing knife", 4), "4 Throwing knives");
+    }
+
+    #[test]
+    fn plurals_of_the_first_word_only() {
+        assert_eq!(counted("Potion of haste", 2), "2 Potions of haste");
+        assert_eq!(counted("Scroll of teleportation", 3), "3 Scrolls of teleportation");
+        assert_eq!(counted("scroll labeled XIXAXA", 2), "2 scrolls labeled XIXAXA");
+    }
+}
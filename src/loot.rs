@@ -0,0 +1,141 @@
+//! Loot tables: which items turn up on the floor, in shops and in the hands
+//! of dead monsters.
+//!
+//! A table is a list of weighted entries. Entries only come into play from a
+//! certain depth on, so deeper levels hand out better things.
+
+use difficulty::Difficulty;
+use entities::{self, Item};
+use rand::Rng;
+use tables;
+
+/// Something a loot table can hand out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loot {
+    /// No item at all, to make the other entries rarer.
+    Nothing,
+    Gold,
+    HealingPotion,
+    RegenerationPotion,
+    HastePotion,
+    ConfusionPotion,
+    Ration,
+    ThrowingKnife,
+    TeleportScroll,
+    MagicMappingScroll,
+    EnchantWeaponScroll,
+    WandOfFireBolt,
+    WandOfSlowMonster,
+}
+
+impl Loot {
+    /// Makes the item, sized for a level of the given difficulty.
+    pub fn create(&self, difficulty: &Difficulty, rng: &mut impl Rng) -> Option<Box<Item>> {
+        use self::Loot::*;
+
+        Some(match self {
+            Nothing => return None,
+            Gold => Box::new(entities::Gold {
+                amount: rng.gen_range(5, 31) + difficulty.gold_bonus,
+            }),
+            HealingPotion => Box::new(entities::HealingPotion {}),
+            RegenerationPotion => Box::new(entities::RegenerationPotion {}),
+            HastePotion => Box::new(entities::HastePotion {}),
+            ConfusionPotion => Box::new(entities::ConfusionPotion {}),
+            Ration => Box::new(entities::Ration {}),
+            ThrowingKnife => Box::new(entities::ThrowingKnife {}),
+            TeleportScroll => Box::new(entities::TeleportScroll {}),
+            MagicMappingScroll => Box::new(entities::MagicMappingScroll {}),
+            EnchantWeaponScroll => Box::new(entities::EnchantWeaponScroll {}),
+            WandOfFireBolt => Box::new(entities::WandOfFireBolt {
+                charges: rng.gen_range(3, 6) + difficulty.wand_charge_bonus,
+            }),
+            WandOfSlowMonster => Box::new(entities::WandOfSlowMonster {
+                charges: rng.gen_range(3, 6) + difficulty.wand_charge_bonus,
+            }),
+        })
+    }
+}
+
+pub type Entry = tables::Entry<Loot>;
+
+/// What lies around in rooms.
+pub const FLOOR: &[Entry] = &[
+    (Loot::Nothing, 12, 1),
+    (Loot::Gold, 8, 1),
+    (Loot::HealingPotion, 3, 1),
+    (Loot::Ration, 3, 1),
+    (Loot::ThrowingKnife, 2, 1),
+    (Loot::ConfusionPotion, 2, 1),
+    (Loot::RegenerationPotion, 2, 2),
+    (Loot::HastePotion, 1, 2),
+    (Loot::TeleportScroll, 2, 1),
+    (Loot::MagicMappingScroll, 1, 2),
+    (Loot::EnchantWeaponScroll, 1, 3),
+    (Loot::WandOfSlowMonster, 1, 3),
+    (Loot::WandOfFireBolt, 1, 4),
+];
+
+/// What shops have for sale. Shops never sell junk.
+pub const SHOP: &[Entry] = &[
+    (Loot::HealingPotion, 4, 1),
+    (Loot::Ration, 3, 1),
+    (Loot::ThrowingKnife, 3, 1),
+    (Loot::RegenerationPotion, 2, 1),
+    (Loot::HastePotion, 2, 1),
+    (Loot::TeleportScroll, 2, 1),
+    (Loot::MagicMappingScroll, 2, 1),
+    (Loot::EnchantWeaponScroll, 1, 1),
+    (Loot::WandOfSlowMonster, 1, 1),
+    (Loot::WandOfFireBolt, 1, 2),
+];
+
+/// What monsters carry, dropped when they die.
+pub const MONSTER_DROPS: &[Entry] = &[
+    (Loot::Nothing, 20, 1),
+    (Loot::Gold, 6, 1),
+    (Loot::ThrowingKnife, 2, 1),
+    (Loot::HealingPotion, 1, 1),
+    (Loot::Ration, 1, 1),
+    (Loot::TeleportScroll, 1, 2),
+    (Loot::EnchantWeaponScroll, 1, 3),
+];
+
+/// Picks an entry of `table` fit for the difficulty's loot depth, and makes
+/// its item.
+pub fn roll(table: &[Entry], difficulty: &Difficulty, rng: &mut impl Rng) -> Option<Box<Item>> {
+    tables::roll(table, difficulty.loot_depth, rng)?.create(difficulty, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helpers::Coords;
+    use rand::thread_rng;
+    use spawns;
+
+    /// Every item the game can hand out, as long as it comes from a loot table
+    /// or a monster's corpse, must have an appearance to be shuffled.
+    #[test]
+    fn every_item_is_registered() {
+        let ids: Vec<_> = entities::item_kinds().iter().map(|item| item.id()).collect();
+        let difficulty = Difficulty::for_depth(1);
+        let mut rng = thread_rng();
+
+        for &(loot, ..) in FLOOR.iter().chain(SHOP).chain(MONSTER_DROPS) {
+            if let Some(item) = loot.create(&difficulty, &mut rng) {
+                assert!(ids.contains(&item.id()), "{:?} is not in item_kinds", loot);
+            }
+        }
+
+        for &(spawn, ..) in spawns::ROOM.iter().chain(spawns::WANDERING) {
+            for monster in spawn.monsters(&mut rng) {
+                let creature = monster.create(Coords::new(0, 0), &difficulty);
+
+                if let Some(corpse) = creature.corpse() {
+                    assert!(ids.contains(&corpse.id()), "{:?} corpse is not registered", monster);
+                }
+            }
+        }
+    }
+}
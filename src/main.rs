@@ -9,99 +9,42 @@ macro_rules! debugln {
 }
 
 mod constants;
+mod difficulty;
 mod entities;
+mod game;
 mod helpers;
+mod highscores;
+mod identification;
+mod loot;
 mod map;
+mod messages;
+mod morgue;
+mod scenes;
+mod spawns;
+mod tables;
 mod tileset;
 
-use entities::Player;
 use ggez::{
     conf::{self, WindowMode}, event, graphics::{self, Color}, Context, GameResult,
 };
-use helpers::{clamp, Coords};
-use map::{
-    generation::{generate_map, MapGenOptions, Simple}, Map,
-};
-use std::{
-    env, path, time::{Duration, Instant},
-};
+use map::generation::MAP_GEN_OPTIONS;
+use scenes::{Scene, TitleScene, Transition, World};
+use std::{env, path, time::Duration};
 use tileset::TileSet;
 
 const TILES_X: i32 = 50;
 const TILES_Y: i32 = 40;
 const TILE_SIZE: i32 = 16;
-const ROOM_WIDTH: std::ops::Range<i32> = 4..8;
-const ROOM_HEIGHT: std::ops::Range<i32> = 4..8;
-const SCALE_FACTOR: f32 = 0.5;
 const DISPLAY_SCALE_FACTOR: f32 = 1.25;
 const DISPLAY_MAP_WIDTH: i32 = (TILES_X as f32 / DISPLAY_SCALE_FACTOR) as i32;
 const DISPLAY_MAP_HEIGHT: i32 = (TILES_Y as f32 / DISPLAY_SCALE_FACTOR) as i32;
-const MAP_WIDTH: i32 = (TILES_X as f32 / SCALE_FACTOR) as i32;
-const MAP_HEIGHT: i32 = (TILES_Y as f32 / SCALE_FACTOR) as i32;
-const MAP_GEN_OPTIONS: MapGenOptions = MapGenOptions {
-    map_width: MAP_WIDTH,
-    map_height: MAP_HEIGHT,
-    room_width: ROOM_WIDTH,
-    room_height: ROOM_HEIGHT,
-    outside_buffer: 2,
-    room_buffer: 2,
-};
-
-#[derive(Debug, Clone, Copy)]
-enum EventType {
-    Combat,
-    Healing,
-    Item,
-}
-
-impl EventType {
-    fn icon(&self) -> &'static str {
-        use self::EventType::*;
-
-        match self {
-            Combat => "axe",
-            Healing => "potion",
-            Item => "$",
-        }
-    }
-
-    fn color(&self) -> Color {
-        use self::EventType::*;
-
-        match self {
-            Combat => Color::from_rgb(191, 0, 0),
-            Healing => Color::from_rgb(0, 191, 0),
-            Item => Color::from_rgb(191, 191, 0),
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
-struct Event {
-    msg: String,
-    ty: EventType,
-    time: Instant,
-    disabled: bool,
-}
-
-impl Event {
-    fn new(msg: String, ty: EventType) -> Event {
-        Event {
-            msg,
-            ty,
-            time: Instant::now(),
-            disabled: false,
-        }
-    }
-}
-
+const MAP_WIDTH: i32 = MAP_GEN_OPTIONS.map_width;
+const MAP_HEIGHT: i32 = MAP_GEN_OPTIONS.map_height;
+const SIGHT_RADIUS: i32 = 10;
+const ACTIVITY_STEP_DELAY: Duration = Duration::from_millis(30);
 struct GameState {
-    ts: TileSet,
-    map: Map,
-    menu_on: bool,
-    menu_cursor_y: i32,
-    player: Player,
-    events: Vec<Event>,
+    world: World,
+    scenes: Vec<Box<Scene>>,
 }
 
 impl GameState {
@@ -110,203 +53,53 @@ impl GameState {
         let mut ts = TileSet::new(image, (32, 8), (16, 16), DISPLAY_SCALE_FACTOR);
 
         constants::register_tiles(&mut ts).unwrap();
-        let map = generate_map::<Simple>(MAP_GEN_OPTIONS);
-        let player_position = (|| {
-            for tile in map.iter() {
-                if tile.tile_type.is_walkable_tile() {
-                    return Coords::new(tile.pos.x, tile.pos.y);
-                }
-            }
-
-            Coords::new(0, 0)
-        })();
 
         Ok(GameState {
-            ts,
-            map,
-            menu_on: false,
-            menu_cursor_y: 0,
-            player: Player::new(player_position),
-            events: Vec::new(),
+            world: World { ts, game: None },
+            scenes: vec![Box::new(TitleScene::new())],
         })
     }
 
-    fn draw_string(
-        &mut self,
-        text: &str,
-        origin: (i32, i32),
-        background_color: Option<Color>,
-        foreground_color: Option<Color>,
-    ) {
-        let mut s = String::with_capacity(1);
-        let mut origin_offset = 0;
-
-        for c in text.chars() {
-            s.push(c);
-
-            if let Err(s) = self.ts.queue_tile_with_background(
-                "solid",
-                &s,
-                (origin.0 + origin_offset, origin.1),
-                if let None = background_color {
-                    Some(Color::from_rgb(0, 0, 0))
-                } else {
-                    background_color
-                },
-                foreground_color,
-            ) {
-                println!("`{}` not found", s);
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.scenes.pop();
             }
-
-            origin_offset += 1;
-            s.pop();
-        }
-    }
-
-    fn draw_events(&mut self) {
-        let time_to_expire = Duration::from_millis(5000);
-        self.events
-            .iter_mut()
-            .filter(|e| !e.disabled && e.time.elapsed() > time_to_expire)
-            .for_each(|e| e.disabled = true);
-
-        let get_transparency = |e: &Event| -> u8 {
-            let elapsed =
-                e.time.elapsed().as_secs() as u32 * 1000 + e.time.elapsed().subsec_millis();
-            let elapsed = elapsed as f64;
-            if elapsed > 3000f64 {
-                255 - (255f64 * ((elapsed - 3000f64) / 2000f64)) as u8
-            } else {
-                255
+            Transition::Replace(scene) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
             }
-        };
-
-        let events: Vec<_> = self.events
-            .iter()
-            .rev()
-            .filter(|e| !e.disabled)
-            .take(5)
-            .map(|s| s.clone())
-            .collect();
-        let len = events.len() as i32;
-        for (i, event) in events.into_iter().enumerate() {
-            let t = get_transparency(&event);
-            let (r, g, b) = event.ty.color().to_rgb();
-            let altered_color: Color = (r, g, b, t).into();
-
-            self.draw_string(
-                "[",
-                (0, DISPLAY_MAP_HEIGHT - (len - i as i32)),
-                Some((0, 0, 0, t).into()),
-                Some((255, 255, 255, t).into()),
-            );
-            self.ts
-                .queue_tile_with_background(
-                    "solid",
-                    event.ty.icon(),
-                    (1, DISPLAY_MAP_HEIGHT - (len - i as i32)),
-                    Some((0, 0, 0, t).into()),
-                    Some(altered_color),
-                )
-                .unwrap();
-            self.draw_string(
-                "]",
-                (2, DISPLAY_MAP_HEIGHT - (len - i as i32)),
-                Some((0, 0, 0, t).into()),
-                Some((255, 255, 255, t).into()),
-            );
-
-            self.draw_string(
-                &event.msg,
-                (3, DISPLAY_MAP_HEIGHT - (len - i as i32)),
-                Some((0, 0, 0, t).into()),
-                Some((255, 255, 255, t).into()),
-            );
+            Transition::Reset(scene) => {
+                self.scenes.clear();
+                self.scenes.push(scene);
+            }
+            Transition::Quit => self.scenes.clear(),
         }
-    }
-
-    fn draw_menu(&mut self) {
-        let center = (0, (TILES_Y as f32 / SCALE_FACTOR) as i32 / 2);
-        let top_left = (
-            center.0,
-            center.1 - (TILES_Y as f32 / SCALE_FACTOR) as i32 / 4,
-        );
-
-        self.ts
-            .queue_rect(
-                "solid",
-                top_left,
-                (TILES_X, TILES_Y / 2),
-                Some(Color::from_rgba(0x00, 0x00, 0x00, 0xFA)),
-            )
-            .unwrap();
-
-        let cursor_offset = self.menu_cursor_y;
-
-        self.draw_string(
-            ">",
-            (5, top_left.1 + 5 + cursor_offset),
-            None,
-            Some(Color::from_rgba(0xFF, 0xFF, 0xFF, 0xFF)),
-        );
 
-        self.draw_string(
-            "1. Menu Text",
-            (6, top_left.1 + 5),
-            None,
-            Some(Color::from_rgba(0xFF, 0xFF, 0xFF, 0xFF)),
-        );
+        if self.scenes.is_empty() {
+            ctx.quit().unwrap();
+        }
     }
 }
 
 impl event::EventHandler for GameState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
-        let player_position = self.player.pos;
-
-        let camera_position = Coords::new(
-            clamp(
-                player_position.x - DISPLAY_MAP_WIDTH / 2,
-                0,
-                DISPLAY_MAP_WIDTH * 2,
-            ),
-            clamp(
-                player_position.y - DISPLAY_MAP_HEIGHT / 2,
-                0,
-                DISPLAY_MAP_HEIGHT * 2,
-            ),
-        );
-
-        self.map
-            .draw(
-                &mut self.ts,
-                |t| {
-                    t.pos >= camera_position && !t.tile_type.is_empty()
-                        && (t.tile_type == map::TileType::Pathway || t.pos != player_position)
-                },
-                camera_position,
-            )
-            .unwrap();
-
-        let player_position = Coords::new(
-            player_position.x - camera_position.x,
-            player_position.y - camera_position.y,
-        );
-
-        self.ts
-            .queue_tile(
-                constants::TILE_SPEC_AT.name,
-                (player_position.x, player_position.y),
-                None,
-            )
-            .unwrap();
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.update(&mut self.world),
+            None => Transition::None,
+        };
 
-        let hp = self.player.hp;
-        self.draw_string(&format!("HP: {}", hp), (0, 0), None, None);
+        self.apply(ctx, transition);
 
-        self.draw_events();
+        let bottom = self.scenes
+            .iter()
+            .rposition(|s| !s.is_overlay())
+            .unwrap_or(0);
 
-        if self.menu_on {
-            self.draw_menu();
+        for scene in self.scenes[bottom..].iter_mut() {
+            scene.draw(&mut self.world);
         }
 
         Ok(())
@@ -315,249 +108,40 @@ impl event::EventHandler for GameState {
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::set_background_color(ctx, Color::from_rgba(0, 0, 0, 1));
         graphics::clear(ctx);
-        self.ts.render(ctx)?;
-        self.ts.clear_queue();
+        self.world.ts.render(ctx)?;
+        self.world.ts.clear_queue();
         graphics::present(ctx);
         Ok(())
     }
 
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: event::MouseButton,
+        x: i32,
+        y: i32,
+    ) {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.mouse_button_down(&mut self.world, button, x, y),
+            None => Transition::None,
+        };
+
+        self.apply(ctx, transition);
+    }
+
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         keycode: event::Keycode,
-        _: event::Mod,
+        keymod: event::Mod,
         repeat: bool,
     ) {
-        use entities::Creature;
-
-        if !repeat {
-            if let event::Keycode::R = keycode {
-                #[cfg(debug_assertions)]
-                let time = std::time::Instant::now();
-
-                self.map = generate_map::<Simple>(MAP_GEN_OPTIONS);
-
-                debugln!("Generation took: {} ms", time.elapsed().subsec_millis());
-            } else if let event::Keycode::M = keycode {
-                self.menu_on = !self.menu_on;
-            } else if let event::Keycode::Down = keycode {
-                if self.menu_on {
-                    if self.menu_cursor_y + 1 > 5 {
-                        self.menu_cursor_y = 0;
-                    } else {
-                        self.menu_cursor_y = clamp(self.menu_cursor_y + 1, 0, 5);
-                    }
-                }
-            } else if let event::Keycode::Up = keycode {
-                if self.menu_on {
-                    self.menu_cursor_y = clamp(self.menu_cursor_y - 1, 0, 5);
-                }
-            } else if let event::Keycode::H = keycode {
-                let Coords { x: p_x, y: p_y } = self.player.pos;
-
-                if let Some(tile) = self.map.tile_at((p_x + 1, p_y)) {
-                    self.map.add_item(tile.pos, entities::HealingPotion {});
-                }
-            } else if let event::Keycode::U = keycode {
-                if let Some(pos) = self.player.inv.iter().position(|i| i.id() == 0) {
-                    let p_hp = self.player.hp;
-                    self.player.inv.remove(pos).consume(&mut self.player);
-                    self.events.push(Event::new(
-                        format!("Player gained {} HP.", self.player.hp - p_hp),
-                        EventType::Healing,
-                    ));
-                }
-            } else if let event::Keycode::G = keycode {
-                let Coords { x: p_x, y: p_y } = self.player.pos;
-
-                if let Some(tile) = self.map.tile_at((p_x + 1, p_y)) {
-                    self.map.add_creature(entities::Goblin::new(tile.pos));
-                }
-            }
-        }
-
-        if let event::Keycode::Left = keycode {
-            if let Some(tile) = self.map.tile_at((self.player.pos.x - 1, self.player.pos.y)) {
-                if let Some(pos) = self.map
-                    .monsters
-                    .iter_mut()
-                    .position(|m| m.pos() == tile.pos)
-                {
-                    let dead = {
-                        let monster = self.map.monsters.get_mut(pos).unwrap();
-
-                        let m_prev_hp = monster.hp();
-                        let p_prev_hp = self.player.hp;
-
-                        self.player.deal_damage(&mut **monster);
-
-                        self.events.push(Event::new(
-                            format!("Monster took {} damage.", m_prev_hp - monster.hp()),
-                            EventType::Combat,
-                        ));
-                        monster.deal_damage(&mut self.player);
-
-                        self.events.push(Event::new(
-                            format!("Player took {} damage.", p_prev_hp - self.player.hp),
-                            EventType::Combat,
-                        ));
-
-                        monster.is_dead()
-                    };
-
-                    if dead {
-                        self.map.monsters.remove(pos);
-                        self.events
-                            .push(Event::new(String::from("Monster died."), EventType::Combat));
-                    }
-                } else if tile.tile_type.is_walkable_tile() {
-                    self.player.pos.x -= 1;
-
-                    let pos = self.player.pos;
-
-                    while let Some(pos) = self.map.items.iter().position(|(p, _)| p == &pos) {
-                        self.player.inv.push(self.map.items.remove(pos).1);
-                        self.events
-                            .push(Event::new(String::from("Picked up item."), EventType::Item));
-                    }
-                }
-            }
-        } else if let event::Keycode::Right = keycode {
-            if let Some(tile) = self.map.tile_at((self.player.pos.x + 1, self.player.pos.y)) {
-                if let Some(pos) = self.map
-                    .monsters
-                    .iter_mut()
-                    .position(|m| m.pos() == tile.pos)
-                {
-                    let dead = {
-                        let monster = self.map.monsters.get_mut(pos).unwrap();
-
-                        let m_prev_hp = monster.hp();
-                        let p_prev_hp = self.player.hp;
-
-                        self.player.deal_damage(&mut **monster);
-
-                        self.events.push(Event::new(
-                            format!("Monster took {} damage.", m_prev_hp - monster.hp()),
-                            EventType::Combat,
-                        ));
-                        monster.deal_damage(&mut self.player);
-
-                        self.events.push(Event::new(
-                            format!("Player took {} damage.", p_prev_hp - self.player.hp),
-                            EventType::Combat,
-                        ));
-                        monster.is_dead()
-                    };
-
-                    if dead {
-                        self.map.monsters.remove(pos);
-                        self.events
-                            .push(Event::new(String::from("Monster died."), EventType::Combat));
-                    }
-                } else if tile.tile_type.is_walkable_tile() {
-                    self.player.pos.x += 1;
-
-                    let pos = self.player.pos;
-
-                    while let Some(pos) = self.map.items.iter().position(|(p, _)| p == &pos) {
-                        self.player.inv.push(self.map.items.remove(pos).1);
-                        self.events
-                            .push(Event::new(String::from("Picked up item."), EventType::Item));
-                    }
-                }
-            }
-        } else if let event::Keycode::Up = keycode {
-            if let Some(tile) = self.map.tile_at((self.player.pos.x, self.player.pos.y - 1)) {
-                if let Some(pos) = self.map
-                    .monsters
-                    .iter_mut()
-                    .position(|m| m.pos() == tile.pos)
-                {
-                    let dead = {
-                        let monster = self.map.monsters.get_mut(pos).unwrap();
-
-                        let m_prev_hp = monster.hp();
-                        let p_prev_hp = self.player.hp;
-
-                        self.player.deal_damage(&mut **monster);
-
-                        self.events.push(Event::new(
-                            format!("Monster took {} damage.", m_prev_hp - monster.hp()),
-                            EventType::Combat,
-                        ));
-                        monster.deal_damage(&mut self.player);
-
-                        self.events.push(Event::new(
-                            format!("Player took {} damage.", p_prev_hp - self.player.hp),
-                            EventType::Combat,
-                        ));
-                        monster.is_dead()
-                    };
-
-                    if dead {
-                        self.map.monsters.remove(pos);
-                        self.events
-                            .push(Event::new(String::from("Monster died."), EventType::Combat));
-                    }
-                } else if tile.tile_type.is_walkable_tile() {
-                    self.player.pos.y -= 1;
-
-                    let pos = self.player.pos;
-
-                    while let Some(pos) = self.map.items.iter().position(|(p, _)| p == &pos) {
-                        self.player.inv.push(self.map.items.remove(pos).1);
-                        self.events
-                            .push(Event::new(String::from("Picked up item."), EventType::Item));
-                    }
-                }
-            }
-        } else if let event::Keycode::Down = keycode {
-            if let Some(tile) = self.map.tile_at((self.player.pos.x, self.player.pos.y + 1)) {
-                if let Some(pos) = self.map
-                    .monsters
-                    .iter_mut()
-                    .position(|m| m.pos() == tile.pos)
-                {
-                    let dead = {
-                        let monster = self.map.monsters.get_mut(pos).unwrap();
-
-                        let m_prev_hp = monster.hp();
-                        let p_prev_hp = self.player.hp;
-
-                        self.player.deal_damage(&mut **monster);
-
-                        self.events.push(Event::new(
-                            format!("Monster took {} damage.", m_prev_hp - monster.hp()),
-                            EventType::Combat,
-                        ));
-                        monster.deal_damage(&mut self.player);
-
-                        self.events.push(Event::new(
-                            format!("Player took {} damage.", p_prev_hp - self.player.hp),
-                            EventType::Combat,
-                        ));
-                        monster.is_dead()
-                    };
-
-                    if dead {
-                        self.map.monsters.remove(pos);
-                        self.events
-                            .push(Event::new(String::from("Monster died."), EventType::Combat));
-                    }
-                } else if tile.tile_type.is_walkable_tile() {
-                    self.player.pos.y += 1;
-
-                    let pos = self.player.pos;
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.key_down(&mut self.world, keycode, keymod, repeat),
+            None => Transition::None,
+        };
 
-                    while let Some(pos) = self.map.items.iter().position(|(p, _)| p == &pos) {
-                        self.player.inv.push(self.map.items.remove(pos).1);
-                        self.events
-                            .push(Event::new(String::from("Picked up item."), EventType::Item));
-                    }
-                }
-            }
-        }
+        self.apply(ctx, transition);
     }
 }
 
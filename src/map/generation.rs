@@ -1,12 +1,15 @@
+use difficulty::Difficulty;
 use ggez::graphics::Color;
 use helpers::*;
+use loot;
 use map::*;
 //use pathfinding::prelude::*;
-use rand::{distributions::Uniform, thread_rng};
+use rand::{distributions::Uniform, thread_rng, Rng};
+use spawns;
 use std::ops::Range;
 
-pub fn generate_map<T: MapGen>(opts: MapGenOptions) -> Map {
-    T::gen(opts)
+pub fn generate_map<T: MapGen>(opts: MapGenOptions, difficulty: &Difficulty) -> Map {
+    T::gen(opts, difficulty)
 }
 
 #[derive(Debug, Clone)]
@@ -19,16 +22,26 @@ pub struct MapGenOptions {
     pub room_buffer: i32,
 }
 
+/// How the first level is laid out. Deeper levels start from this and scale it
+/// through `difficulty`.
+pub const MAP_GEN_OPTIONS: MapGenOptions = MapGenOptions {
+    map_width: 100,
+    map_height: 80,
+    room_width: 4..8,
+    room_height: 4..8,
+    outside_buffer: 2,
+    room_buffer: 2,
+};
+
 pub trait MapGen {
-    fn gen(options: MapGenOptions) -> Map;
+    /// Lays out a level, filled with what `difficulty` calls for.
+    fn gen(options: MapGenOptions, difficulty: &Difficulty) -> Map;
 }
 
 pub struct Simple;
 
 impl MapGen for Simple {
-    fn gen(options: MapGenOptions) -> Map {
-        use rand::Rng;
-
+    fn gen(options: MapGenOptions, difficulty: &Difficulty) -> Map {
         let mut rooms = Vec::new();
 
         let mut rng = thread_rng();
@@ -48,6 +61,7 @@ impl MapGen for Simple {
 
         let mut map = Map::new(options.map_width, options.map_height);
         let mut doors = Vec::new();
+        let mut traps = Vec::new();
 
         for _ in 0..=100 {
             let room = Rect::random_rect(&mut rng, &x_uniform, &y_uniform, &w_uniform, &h_uniform);
@@ -72,20 +86,62 @@ impl MapGen for Simple {
                 let choice = door_options[thread_rng().gen_range(0, 4)];
 
                 map[(choice.1 * options.map_width + choice.0) as usize] =
-                    Tile::new(TileType::Door, choice, None);
+                    Tile::new(TileType::Door { open: false }, choice, None);
 
                 doors.push(choice);
 
+                for _ in 0..difficulty.item_rolls_per_room {
+                    if let Some(item) = loot::roll(loot::FLOOR, difficulty, &mut rng) {
+                        let spot = Coords::new(
+                            rng.gen_range(room.left() + 1, room.right()),
+                            rng.gen_range(room.top() + 1, room.bottom()),
+                        );
+
+                        map.items.push((spot, item));
+                    }
+                }
+
+                // The player starts in the first room, so it stays free of traps.
+                if !rooms.is_empty() && rng.gen_range(0, 4) == 0 {
+                    traps.push((
+                        rng.gen_range(room.left() + 1, room.right()),
+                        rng.gen_range(room.top() + 1, room.bottom()),
+                    ));
+                }
+
                 rooms.push(room);
             }
         }
 
+        // Half the levels have a shop, never in the room the player starts in.
+        let mut shop_index = None;
+
+        if rooms.len() > 1 && rng.gen() {
+            let index = rng.gen_range(1, rooms.len());
+            let room = rooms[index];
+            shop_index = Some(index);
+            let (x, y) = room.center();
+
+            traps.retain(|&spot| !room.contains(spot));
+            map.items.retain(|(pos, _)| !room.contains((pos.x, pos.y)));
+
+            map.shop = Some(Shop {
+                room,
+                keeper: Coords::new(x, y),
+                stock: (0..rng.gen_range(4, 8))
+                    .filter_map(|_| loot::roll(loot::SHOP, difficulty, &mut rng))
+                    .collect(),
+            });
+        }
+
+        let mut grid = pathfinding::PathGrid::new(map.width(), map.height());
+
         for (door1, door2) in doors
             .iter()
             .zip(doors.iter().skip(1))
             .map(|(&(x1, y1), &(x2, y2))| ((x1 as i32, y1 as i32), (x2 as i32, y2 as i32)))
         {
-            let path = pathfinding::ortho_star(
+            let path = grid.ortho_star(
                 door1,
                 door2,
                 |x, y| {
@@ -103,6 +159,14 @@ impl MapGen for Simple {
             let color = Color::from_rgb(20, 20, 20);
 
             if let Some(path) = path {
+                if path.len() > 2 && rng.gen_range(0, 3) == 0 {
+                    let spot = path[rng.gen_range(1, path.len() - 1)];
+
+                    if !doors.contains(&spot) && !traps.contains(&spot) {
+                        traps.push(spot);
+                    }
+                }
+
                 for (x, y) in path {
                     let (x, y) = (x as i32, y as i32);
 
@@ -114,6 +178,27 @@ impl MapGen for Simple {
             }
         }
 
+        let trap_kinds = [TrapKind::Dart, TrapKind::Teleport, TrapKind::Alarm];
+
+        for spot in traps {
+            let kind = *rng.choose(&trap_kinds).unwrap();
+            map.traps.push(Trap::new(spot.into(), kind));
+        }
+
+        // Monsters keep out of the starting room, and out of the shop.
+        for (index, &room) in rooms.iter().enumerate().skip(1) {
+            if shop_index == Some(index) {
+                continue;
+            }
+
+            for _ in 0..difficulty.spawn_rolls_per_room {
+                let spawn = spawns::roll(spawns::ROOM, difficulty, &mut rng);
+                spawns::populate_room(&mut map, room, spawn, difficulty, &mut rng);
+            }
+        }
+
+        map.rooms = rooms;
+
         map
     }
 }
@@ -1,20 +1,27 @@
 pub mod generation;
-mod pathfinding;
+pub mod pathfinding;
 
 use constants::*;
 use entities;
 use ggez::graphics::Color;
-use helpers::{Coords, Rect};
+use helpers::{self, Coords, Rect};
 use rand::{
-    distributions::{Distribution, Weighted, WeightedChoice}, thread_rng,
+    distributions::{Distribution, Weighted, WeightedChoice}, thread_rng, Rng,
 };
 use std::ops::{Deref, DerefMut};
 
 pub struct Map {
     tiles: Vec<Tile>,
     width: i32,
+    height: i32,
+    explored: Vec<bool>,
+    visible: Vec<bool>,
     pub items: Vec<(Coords, Box<entities::Item>)>,
     pub monsters: Vec<Box<entities::Creature>>,
+    pub traps: Vec<Trap>,
+    /// Every room on the level. The player starts in the first one.
+    pub rooms: Vec<Rect>,
+    pub shop: Option<Shop>,
 }
 
 impl Map {
@@ -30,8 +37,58 @@ impl Map {
                 })
                 .collect(),
             width,
+            height,
+            explored: vec![false; (width * height) as usize],
+            visible: vec![false; (width * height) as usize],
             items: Vec::new(),
             monsters: Vec::new(),
+            traps: Vec::new(),
+            rooms: Vec::new(),
+            shop: None,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn index(&self, coords: Coords) -> Option<usize> {
+        if coords.x < 0 || coords.y < 0 || coords.x >= self.width || coords.y >= self.height {
+            None
+        } else {
+            Some((coords.y * self.width + coords.x) as usize)
+        }
+    }
+
+    pub fn is_explored(&self, coords: Coords) -> bool {
+        self.index(coords).is_some_and(|i| self.explored[i])
+    }
+
+    pub fn is_visible(&self, coords: Coords) -> bool {
+        self.index(coords).is_some_and(|i| self.visible[i])
+    }
+
+    /// Recomputes which tiles can be seen from `center`, marking them explored.
+    pub fn update_fov(&mut self, center: Coords, radius: i32) {
+        for visible in self.visible.iter_mut() {
+            *visible = false;
+        }
+
+        for y in center.y - radius..=center.y + radius {
+            for x in center.x - radius..=center.x + radius {
+                let coords = Coords::new(x, y);
+
+                if let Some(index) = self.index(coords) {
+                    if self.can_see(center, coords, radius) {
+                        self.visible[index] = true;
+                        self.explored[index] = true;
+                    }
+                }
+            }
         }
     }
 
@@ -39,10 +96,113 @@ impl Map {
         self.items.push((coords, Box::new(item)));
     }
 
+    #[allow(dead_code)]
     pub fn add_creature<C: entities::Creature + 'static>(&mut self, creature: C) {
         self.monsters.push(Box::new(creature));
     }
 
+    pub fn monster_at(&self, coords: Coords) -> Option<&entities::Creature> {
+        self.monsters.iter().find(|m| m.pos() == coords).map(|m| &**m)
+    }
+
+    pub fn items_at(&self, coords: Coords) -> impl Iterator<Item = &Box<entities::Item>> {
+        self.items
+            .iter()
+            .filter(move |(pos, _)| *pos == coords)
+            .map(|(_, item)| item)
+    }
+
+    pub fn is_shopkeeper(&self, coords: Coords) -> bool {
+        self.shop.as_ref().is_some_and(|s| s.keeper == coords)
+    }
+
+    /// Whether a trap the player knows about sits at `coords`.
+    pub fn is_known_trap(&self, coords: Coords) -> bool {
+        self.traps.iter().any(|t| t.pos == coords && !t.hidden)
+    }
+
+    pub fn trap_at_mut(&mut self, coords: Coords) -> Option<&mut Trap> {
+        self.traps.iter_mut().find(|t| t.pos == coords)
+    }
+
+    /// Picks a random walkable tile that no monster, shopkeeper or the player
+    /// at `player` is standing on.
+    pub fn random_walkable_tile(&self, player: Coords, rng: &mut impl Rng) -> Option<Coords> {
+        let tiles: Vec<_> = self.tiles
+            .iter()
+            .filter(|t| {
+                t.tile_type.is_walkable_tile() && self.monster_at(t.pos).is_none()
+                    && !self.is_shopkeeper(t.pos) && t.pos != player
+            })
+            .map(|t| t.pos)
+            .collect();
+
+        rng.choose(&tiles).copied()
+    }
+
+    /// The tiles something flying from `from` towards `to` passes through. It
+    /// stops short of anything that blocks sight and of the shopkeeper, and at
+    /// the first monster.
+    pub fn projectile_path(&self, from: Coords, to: Coords) -> Vec<Coords> {
+        let mut path = Vec::new();
+
+        for point in helpers::line(from, to).into_iter().skip(1) {
+            match self.tile_at(point) {
+                Some(tile) if !tile.tile_type.blocks_sight() && !self.is_shopkeeper(point) => {
+                    path.push(point)
+                }
+                _ => break,
+            }
+
+            if self.monster_at(point).is_some() {
+                break;
+            }
+        }
+
+        path
+    }
+
+    /// The monster a bolt fired from `from` towards `to` hits, if any.
+    pub fn bolt_target(
+        &mut self,
+        from: Coords,
+        to: Coords,
+    ) -> Option<&mut Box<entities::Creature>> {
+        let landing = self.projectile_path(from, to).pop()?;
+
+        self.monsters.iter_mut().find(|m| m.pos() == landing)
+    }
+
+    /// Marks every tile of the level as explored.
+    pub fn reveal(&mut self) {
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if !tile.tile_type.is_empty() && index < self.explored.len() {
+                self.explored[index] = true;
+            }
+        }
+    }
+
+    /// Whether `to` can be seen from `from`, meaning it is within `radius` tiles
+    /// and no tile between the two blocks sight.
+    pub fn can_see(&self, from: Coords, to: Coords, radius: i32) -> bool {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+
+        if dx * dx + dy * dy > radius * radius {
+            return false;
+        }
+
+        let points = helpers::line(from, to);
+
+        if points.len() <= 2 {
+            return true;
+        }
+
+        points[1..points.len() - 1].iter().all(|&p| match self.tile_at(p) {
+            Some(tile) => !tile.tile_type.blocks_sight(),
+            None => false,
+        })
+    }
+
     pub fn draw<F>(
         &self,
         tileset: &mut super::tileset::TileSet,
@@ -59,7 +219,10 @@ impl Map {
             tileset.queue_tile(tile.tile_type.name(), (draw_x, draw_y), tile.color)?;
         }
 
-        for (pos, item) in self.items.iter().filter(|(pos, _)| pos >= &camera) {
+        for (pos, item) in self.items
+            .iter()
+            .filter(|(pos, _)| pos >= &camera && self.is_explored(*pos))
+        {
             let draw_x = pos.x - camera.x;
             let draw_y = pos.y - camera.y;
 
@@ -70,7 +233,32 @@ impl Map {
             )?;
         }
 
-        for monster in self.monsters.iter().filter(|m| m.pos() >= camera) {
+        for trap in self.traps
+            .iter()
+            .filter(|t| !t.hidden && t.pos >= camera && self.is_explored(t.pos))
+        {
+            let draw_x = trap.pos.x - camera.x;
+            let draw_y = trap.pos.y - camera.y;
+
+            tileset.queue_tile(TILE_TRAP.name, (draw_x, draw_y), Some(trap.kind.color()))?;
+        }
+
+        if let Some(ref shop) = self.shop {
+            if shop.keeper >= camera && self.is_visible(shop.keeper) {
+                tileset.queue_tile_with_background(
+                    "solid",
+                    "@",
+                    (shop.keeper.x - camera.x, shop.keeper.y - camera.y),
+                    Some(Color::from_rgb(0, 0, 0)),
+                    Some(Color::from_rgb(100, 149, 237)),
+                )?;
+            }
+        }
+
+        for monster in self.monsters
+            .iter()
+            .filter(|m| m.pos() >= camera && self.is_visible(m.pos()))
+        {
             let pos = monster.pos();
             let draw_x = pos.x - camera.x;
             let draw_y = pos.y - camera.y;
@@ -88,11 +276,15 @@ impl Map {
     }
 
     pub fn tile_at<T: Into<Coords>>(&self, coords: T) -> Option<Tile> {
-        let coords = coords.into();
+        let index = self.index(coords.into())?;
+
+        self.tiles.get(index).map(|&t| t)
+    }
+
+    pub fn tile_at_mut<T: Into<Coords>>(&mut self, coords: T) -> Option<&mut Tile> {
+        let index = self.index(coords.into())?;
 
-        self.tiles
-            .get((coords.y * self.width + coords.x) as usize)
-            .map(|&t| t)
+        self.tiles.get_mut(index)
     }
 }
 
@@ -119,7 +311,7 @@ pub enum TileType {
     LightScatterRoomFloor,
     Pathway,
     Wall,
-    Door,
+    Door { open: bool },
 }
 
 impl TileType {
@@ -141,11 +333,17 @@ impl TileType {
             | LightScatterRoomFloor
             | Grass
             | Pathway
-            | Door => true,
+            | Door { open: true } => true,
             _ => false,
         }
     }
 
+    pub fn blocks_sight(&self) -> bool {
+        use self::TileType::*;
+
+        matches!(self, Empty | Wall | Door { open: false })
+    }
+
     pub fn is_empty(&self) -> bool {
         use self::TileType::*;
 
@@ -155,6 +353,22 @@ impl TileType {
         }
     }
 
+    pub fn description(&self) -> &'static str {
+        use self::TileType::*;
+
+        match self {
+            Empty => "Solid rock",
+            BlankRoomFloor => "Floor",
+            Grass => "Grass",
+            HeavyScatterRoomFloor => "Rubble-strewn floor",
+            LightScatterRoomFloor => "Dusty floor",
+            Pathway => "Corridor",
+            Wall => "Wall",
+            Door { open: true } => "Open door",
+            Door { open: false } => "Closed door",
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         use self::TileType::*;
 
@@ -166,11 +380,77 @@ impl TileType {
             LightScatterRoomFloor => TILE_ROOM_FLRSCLGT.name,
             Pathway => TILE_PATH.name,
             Wall => TILE_ROOM_WALL.name,
-            Door => TILE_DOOR.name,
+            Door { open: true } => TILE_DOOR_OPEN.name,
+            Door { open: false } => TILE_DOOR.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrapKind {
+    Dart,
+    Teleport,
+    Alarm,
+}
+
+impl TrapKind {
+    pub fn name(&self) -> &'static str {
+        use self::TrapKind::*;
+
+        match self {
+            Dart => "dart trap",
+            Teleport => "teleport trap",
+            Alarm => "alarm trap",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        use self::TrapKind::*;
+
+        match self {
+            Dart => Color::from_rgb(191, 63, 63),
+            Teleport => Color::from_rgb(127, 63, 255),
+            Alarm => Color::from_rgb(255, 127, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Trap {
+    pub pos: Coords,
+    pub kind: TrapKind,
+    pub hidden: bool,
+}
+
+impl Trap {
+    pub fn new(pos: Coords, kind: TrapKind) -> Trap {
+        Trap {
+            pos,
+            kind,
+            hidden: true,
         }
     }
 }
 
+/// A room run by a shopkeeper, who trades with the player when bumped into.
+pub struct Shop {
+    pub room: Rect,
+    pub keeper: Coords,
+    pub stock: Vec<Box<entities::Item>>,
+}
+
+impl Shop {
+    /// What the shopkeeper asks for `item`.
+    pub fn price(&self, item: &entities::Item) -> u32 {
+        item.value()
+    }
+
+    /// What the shopkeeper pays for `item`.
+    pub fn offer(&self, item: &entities::Item) -> u32 {
+        item.value() / 2
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Tile {
     pub pos: Coords,
@@ -1,84 +1,467 @@
 //! The module for pathing stuff.
 
-#![allow(unused_mut)]
+use super::Map;
+use helpers::Coords;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
 
-use std::collections::HashMap;
-use std::collections::HashSet;
+const NO_PARENT: usize = usize::MAX;
+const UNREACHABLE: i32 = i32::MAX;
 
-/// Computes a path with A* using only orthographic movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Node {
+    f_score: u32,
+    g_score: u32,
+    index: usize,
+}
+
+// `BinaryHeap` is a max-heap, so the ordering is flipped to pop the lowest f
+// score first. Ties go to the node furthest along its path.
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        other
+            .f_score
+            .cmp(&self.f_score)
+            .then_with(|| self.g_score.cmp(&other.g_score))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Scratch space for path searches over a `width` x `height` grid.
 ///
-/// You must provide a function that determines if a location is walkable, and
-/// also another function that gives the move cost to go from A to B. The second
-/// function will never be called if the first function declares B to not be
-/// walkable, if that helps you somehow.
-pub fn ortho_star<W, MC>(
-    start: (i32, i32),
-    goal: (i32, i32),
-    walkable: W,
-    move_cost: MC,
-) -> Option<Vec<(i32, i32)>>
-where
-    W: Fn(i32, i32) -> bool,
-    MC: Fn((i32, i32), (i32, i32)) -> u32,
-{
-    if !walkable(goal.0, goal.1) {
-        return None;
-    } else {
+/// The buffers are allocated once and reused by every search, so keep one
+/// around when running many searches over the same map.
+pub struct PathGrid {
+    width: i32,
+    height: i32,
+    g_score: Vec<u32>,
+    came_from: Vec<usize>,
+    closed: Vec<bool>,
+    open_set: BinaryHeap<Node>,
+}
+
+impl PathGrid {
+    pub fn new(width: i32, height: i32) -> PathGrid {
+        let size = (width * height) as usize;
+
+        PathGrid {
+            width,
+            height,
+            g_score: vec![u32::MAX; size],
+            came_from: vec![NO_PARENT; size],
+            closed: vec![false; size],
+            open_set: BinaryHeap::new(),
+        }
+    }
+
+    fn index(&self, (x, y): (i32, i32)) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            None
+        } else {
+            Some((y * self.width + x) as usize)
+        }
+    }
+
+    fn coords(&self, index: usize) -> (i32, i32) {
+        (index as i32 % self.width, index as i32 / self.width)
+    }
+
+    fn reset(&mut self) {
+        for g in self.g_score.iter_mut() {
+            *g = u32::MAX;
+        }
+
+        for parent in self.came_from.iter_mut() {
+            *parent = NO_PARENT;
+        }
+
+        for closed in self.closed.iter_mut() {
+            *closed = false;
+        }
+
+        self.open_set.clear();
+    }
+
+    /// Computes a path with A* using only orthographic movement.
+    ///
+    /// You must provide a function that determines if a location is walkable, and
+    /// also another function that gives the move cost to go from A to B. The second
+    /// function will never be called if the first function declares B to not be
+    /// walkable, if that helps you somehow.
+    ///
+    /// The returned path runs from `start` to `goal`, including both.
+    pub fn ortho_star<W, MC>(
+        &mut self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        walkable: W,
+        move_cost: MC,
+    ) -> Option<Vec<(i32, i32)>>
+    where
+        W: Fn(i32, i32) -> bool,
+        MC: Fn((i32, i32), (i32, i32)) -> u32,
+    {
+        let (start_index, goal_index) = match (self.index(start), self.index(goal)) {
+            (Some(start_index), Some(goal_index)) => (start_index, goal_index),
+            _ => return None,
+        };
+
+        if !walkable(goal.0, goal.1) {
+            return None;
+        }
+
         // default heuristic is the manhattan dist
-        let heuristic = |start: (i32, i32), goal: (i32, i32)| {
-            (start.0 - goal.0).abs() as u32 + (start.1 - goal.1).abs() as u32
+        let heuristic = |(x, y): (i32, i32)| (x - goal.0).abs() as u32 + (y - goal.1).abs() as u32;
+
+        self.reset();
+        self.g_score[start_index] = 0;
+        self.open_set.push(Node {
+            f_score: heuristic(start),
+            g_score: 0,
+            index: start_index,
+        });
+
+        while let Some(Node { g_score, index, .. }) = self.open_set.pop() {
+            if index == goal_index {
+                return Some(self.reconstruct_path(index));
+            }
+
+            if self.closed[index] {
+                continue;
+            }
+
+            self.closed[index] = true;
+
+            let current = self.coords(index);
+
+            for &neighbor in [
+                (current.0 + 1, current.1),
+                (current.0 - 1, current.1),
+                (current.0, current.1 + 1),
+                (current.0, current.1 - 1),
+            ].iter()
+            {
+                let neighbor_index = match self.index(neighbor) {
+                    Some(i) if !self.closed[i] => i,
+                    _ => continue,
+                };
+
+                if !walkable(neighbor.0, neighbor.1) {
+                    continue;
+                }
+
+                let tentative_g_score = g_score.saturating_add(move_cost(current, neighbor));
+
+                if tentative_g_score < self.g_score[neighbor_index] {
+                    self.came_from[neighbor_index] = index;
+                    self.g_score[neighbor_index] = tentative_g_score;
+                    self.open_set.push(Node {
+                        f_score: tentative_g_score.saturating_add(heuristic(neighbor)),
+                        g_score: tentative_g_score,
+                        index: neighbor_index,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    fn reconstruct_path(&self, mut current: usize) -> Vec<(i32, i32)> {
+        let mut total_path = vec![self.coords(current)];
+
+        while self.came_from[current] != NO_PARENT {
+            current = self.came_from[current];
+            total_path.push(self.coords(current));
+        }
+
+        total_path.reverse();
+        total_path
+    }
+}
+
+/// A flow field holding, for every walkable tile of a `Map`, the number of
+/// orthogonal steps to the nearest goal tile.
+///
+/// One map serves every creature heading for the same goals: each of them just
+/// rolls downhill with `step_from`.
+pub struct DijkstraMap {
+    width: i32,
+    height: i32,
+    values: Vec<i32>,
+    walkable: Vec<bool>,
+}
+
+impl DijkstraMap {
+    pub fn new(map: &Map, goals: &[Coords]) -> DijkstraMap {
+        DijkstraMap::with_walkable(map, goals, |c| match map.tile_at(c) {
+            Some(tile) => tile.tile_type.is_walkable_tile(),
+            None => false,
+        })
+    }
+
+    /// Like `new`, but only spreads over the tiles `walkable` accepts. Goals
+    /// get their value either way.
+    pub fn with_walkable<F>(map: &Map, goals: &[Coords], walkable: F) -> DijkstraMap
+    where
+        F: Fn(Coords) -> bool,
+    {
+        let (width, height) = (map.width(), map.height());
+        let walkable = (0..width * height)
+            .map(|i| walkable(Coords::new(i % width, i / width)))
+            .collect();
+
+        let mut dijkstra_map = DijkstraMap {
+            width,
+            height,
+            values: vec![UNREACHABLE; (width * height) as usize],
+            walkable,
         };
-        let mut closed_set: HashSet<(i32, i32)> = HashSet::new();
-        let mut came_from: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
-        let mut open_set: HashSet<(i32, i32)> = HashSet::new();
-        open_set.insert(start);
-        let mut g_score: HashMap<(i32, i32), u32> = HashMap::new();
-        g_score.insert(start, 0u32);
-        let mut f_score: HashMap<(i32, i32), u32> = HashMap::new();
-        f_score.insert(start, heuristic(start, goal));
-        while !open_set.is_empty() {
-            let current = *open_set
+
+        for &goal in goals {
+            if let Some(index) = dijkstra_map.index(goal) {
+                dijkstra_map.values[index] = 0;
+            }
+        }
+
+        dijkstra_map.relax();
+        dijkstra_map
+    }
+
+    /// Builds a safety map from this one. Rolling downhill on it leads away from
+    /// the goals, preferring open escape routes over the nearest dead end.
+    pub fn inverted(&self) -> DijkstraMap {
+        let mut dijkstra_map = DijkstraMap {
+            width: self.width,
+            height: self.height,
+            values: self.values
                 .iter()
-                .min_by_key(|loc_ref| f_score[loc_ref])
-                .expect("the open set can never be empty here!");
-            if current == goal {
-                return Some(reconstruct_path(came_from, current));
-            } else {
-                open_set.remove(&current);
-                closed_set.insert(current);
-                for &neighbor in [
-                    (current.0 + 1, current.1),
-                    (current.0 - 1, current.1),
-                    (current.0, current.1 + 1),
-                    (current.0, current.1 - 1),
-                ].iter()
-                    .filter(|(x, y)| walkable(*x, *y) && !closed_set.contains(&(*x, *y)))
-                {
-                    open_set.insert(neighbor);
-                    let tentative_g_score =
-                        g_score[&current].saturating_add(move_cost(current, neighbor));
-                    if tentative_g_score >= *g_score.entry(neighbor).or_insert(::std::u32::MAX) {
-                        continue;
-                    } else {
-                        came_from.insert(neighbor, current);
-                        g_score.insert(neighbor, tentative_g_score);
-                        f_score.insert(neighbor, g_score[&neighbor] + heuristic(neighbor, goal));
-                    }
+                .map(|&v| if v == UNREACHABLE { v } else { -(v * 6) / 5 })
+                .collect(),
+            walkable: self.walkable.clone(),
+        };
+
+        dijkstra_map.relax();
+        dijkstra_map
+    }
+
+    /// The value at `coords`, or `None` if no goal can be reached from there.
+    pub fn value(&self, coords: Coords) -> Option<i32> {
+        self.index(coords)
+            .map(|index| self.values[index])
+            .filter(|&v| v != UNREACHABLE)
+    }
+
+    /// The neighbor of `from` with the lowest value, as long as it is lower than
+    /// the value at `from` and `blocked` doesn't reject it.
+    pub fn step_from<F>(&self, from: Coords, blocked: F) -> Option<Coords>
+    where
+        F: Fn(Coords) -> bool,
+    {
+        let current = self.value(from)?;
+        let Coords { x, y } = from;
+
+        // Goals can sit on tiles that aren't walkable, like a closed door, so any
+        // neighbor with a value is a candidate.
+        [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+            .iter()
+            .map(Coords::from)
+            .filter(|&c| !blocked(c))
+            .filter_map(|c| self.value(c).map(|v| (c, v)))
+            .filter(|&(_, v)| v < current)
+            .min_by_key(|&(_, v)| v)
+            .map(|(c, _)| c)
+    }
+
+    fn index(&self, coords: Coords) -> Option<usize> {
+        if coords.x < 0 || coords.y < 0 || coords.x >= self.width || coords.y >= self.height {
+            None
+        } else {
+            Some((coords.y * self.width + coords.x) as usize)
+        }
+    }
+
+    fn neighbors(&self, Coords { x, y }: Coords) -> Vec<Coords> {
+        [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+            .iter()
+            .map(Coords::from)
+            .filter(|&c| self.index(c).is_some_and(|i| self.walkable[i]))
+            .collect()
+    }
+
+    fn relax(&mut self) {
+        let mut open_set: BinaryHeap<_> = self.values
+            .iter()
+            .enumerate()
+            .filter(|&(_, &v)| v != UNREACHABLE)
+            .map(|(index, &v)| (Reverse(v), index))
+            .collect();
+
+        while let Some((Reverse(value), index)) = open_set.pop() {
+            if value > self.values[index] {
+                continue;
+            }
+
+            let coords = Coords::new(index as i32 % self.width, index as i32 / self.width);
+
+            for neighbor in self.neighbors(coords) {
+                let neighbor_index = (neighbor.y * self.width + neighbor.x) as usize;
+
+                if value + 1 < self.values[neighbor_index] {
+                    self.values[neighbor_index] = value + 1;
+                    open_set.push((Reverse(value + 1), neighbor_index));
                 }
             }
         }
-        None
     }
 }
 
-fn reconstruct_path(
-    came_from: HashMap<(i32, i32), (i32, i32)>,
-    mut current: (i32, i32),
-) -> Vec<(i32, i32)> {
-    let mut total_path = vec![current];
-    while came_from.contains_key(&current) {
-        current = came_from[&current];
-        total_path.push(current);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::TileType;
+
+    /// Parses a grid drawn with `#` for walls and anything else for floor.
+    fn grid(rows: &[&str]) -> (i32, i32, Vec<Vec<bool>>) {
+        let walkable: Vec<Vec<bool>> = rows.iter()
+            .map(|row| row.chars().map(|c| c != '#').collect())
+            .collect();
+
+        (rows[0].len() as i32, rows.len() as i32, walkable)
+    }
+
+    fn search(rows: &[&str], start: (i32, i32), goal: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        let (width, height, walkable) = grid(rows);
+
+        PathGrid::new(width, height).ortho_star(
+            start,
+            goal,
+            |x, y| walkable[y as usize][x as usize],
+            |_, _| 1,
+        )
+    }
+
+    /// Builds a `Map` out of the same kind of drawing.
+    fn map(rows: &[&str]) -> Map {
+        let (width, height, walkable) = grid(rows);
+        let mut map = Map::new(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                if walkable[y as usize][x as usize] {
+                    map.tile_at_mut((x, y)).unwrap().tile_type = TileType::BlankRoomFloor;
+                }
+            }
+        }
+
+        map
+    }
+
+    #[test]
+    fn ortho_star_finds_shortest_path_around_walls() {
+        let rows = [
+            "..#..",
+            "..#..",
+            ".....",
+        ];
+        let path = search(&rows, (0, 0), (4, 0)).unwrap();
+
+        // Down to the gap under the wall, across, and back up.
+        assert_eq!(path.len(), 9);
+
+        for step in path.windows(2) {
+            let ((x1, y1), (x2, y2)) = (step[0], step[1]);
+            assert_eq!((x1 - x2).abs() + (y1 - y2).abs(), 1);
+            assert!(rows[y2 as usize].as_bytes()[x2 as usize] != b'#');
+        }
+    }
+
+    #[test]
+    fn ortho_star_runs_from_start_to_goal() {
+        let rows = ["....", "....", "...."];
+        let path = search(&rows, (0, 0), (3, 2)).unwrap();
+
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(3, 2)));
+        assert_eq!(path.len(), 6);
+    }
+
+    #[test]
+    fn ortho_star_gives_up_on_unreachable_goal() {
+        let rows = [
+            "..#..",
+            "..#..",
+            "..#..",
+        ];
+
+        assert_eq!(search(&rows, (0, 0), (4, 2)), None);
+    }
+
+    #[test]
+    fn ortho_star_rejects_out_of_bounds_and_blocked_goals() {
+        let rows = ["...", ".#.", "..."];
+
+        assert_eq!(search(&rows, (-1, 0), (2, 2)), None);
+        assert_eq!(search(&rows, (0, 0), (3, 0)), None);
+        assert_eq!(search(&rows, (0, 0), (0, 3)), None);
+        assert_eq!(search(&rows, (0, 0), (1, 1)), None);
+    }
+
+    #[test]
+    fn ortho_star_starts_from_unwalkable_tile() {
+        // The generator paths from door to door, and doors don't count as
+        // walkable there, so the start tile must not be checked.
+        let rows = ["#..", "...", "..."];
+        let path = search(&rows, (0, 0), (2, 2)).unwrap();
+
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.len(), 5);
+    }
+
+    #[test]
+    fn step_from_heads_for_goals() {
+        let dijkstra_map = DijkstraMap::new(&map(&[".......", "...#..."]), &[Coords::new(0, 0)]);
+
+        assert_eq!(dijkstra_map.value(Coords::new(3, 0)), Some(3));
+        assert_eq!(
+            dijkstra_map.step_from(Coords::new(3, 0), |_| false),
+            Some(Coords::new(2, 0))
+        );
+        assert_eq!(dijkstra_map.step_from(Coords::new(0, 0), |_| false), None);
+    }
+
+    #[test]
+    fn inverted_map_leads_away_from_goals() {
+        let dijkstra_map = DijkstraMap::new(&map(&["......."]), &[Coords::new(0, 0)]);
+        let safety = dijkstra_map.inverted();
+
+        assert_eq!(
+            safety.step_from(Coords::new(3, 0), |_| false),
+            Some(Coords::new(4, 0))
+        );
+        assert_eq!(
+            safety.step_from(Coords::new(0, 0), |_| false),
+            Some(Coords::new(1, 0))
+        );
+    }
+
+    #[test]
+    fn step_from_respects_blocked() {
+        let dijkstra_map = DijkstraMap::new(&map(&[".......", "......."]), &[Coords::new(0, 0)]);
+        let from = Coords::new(3, 0);
+
+        assert_eq!(dijkstra_map.step_from(from, |c| c == Coords::new(2, 0)), None);
+
+        // Another way downhill is still taken.
+        let from = Coords::new(3, 1);
+        assert_eq!(
+            dijkstra_map.step_from(from, |c| c == Coords::new(2, 1)),
+            Some(Coords::new(3, 0))
+        );
     }
-    total_path
 }
@@ -0,0 +1,61 @@
+//! Messages for the event log.
+
+use ggez::graphics::Color;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy)]
+pub enum EventType {
+    Combat,
+    Healing,
+    Info,
+    Item,
+    Status,
+    Trap,
+}
+
+impl EventType {
+    pub fn icon(&self) -> &'static str {
+        use self::EventType::*;
+
+        match self {
+            Combat => "axe",
+            Healing => "potion",
+            Info => "!",
+            Item => "$",
+            Status => "*",
+            Trap => "trap",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        use self::EventType::*;
+
+        match self {
+            Combat => Color::from_rgb(191, 0, 0),
+            Healing => Color::from_rgb(0, 191, 0),
+            Info => Color::from_rgb(191, 191, 191),
+            Item => Color::from_rgb(191, 191, 0),
+            Status => Color::from_rgb(191, 63, 191),
+            Trap => Color::from_rgb(255, 127, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub msg: String,
+    pub ty: EventType,
+    pub time: Instant,
+    pub disabled: bool,
+}
+
+impl Event {
+    pub fn new(msg: String, ty: EventType) -> Event {
+        Event {
+            msg,
+            ty,
+            time: Instant::now(),
+            disabled: false,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Morgue files: a plain-text record of a finished run, written so runs can
+//! be shared and compared.
+
+use entities::Creature;
+use game::Game;
+use helpers::Coords;
+use identification;
+use map::TileType;
+use std::{
+    fmt::Write as FmtWrite, fs, io::{self, Write}, path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Where morgue files end up, relative to the working directory.
+const MORGUE_DIR: &str = "morgue";
+/// How many of the most recent messages make it into the file.
+const MORGUE_MESSAGES: usize = 20;
+
+/// Writes the morgue file for `game` and returns its path.
+pub fn write(game: &Game) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    fs::create_dir_all(MORGUE_DIR)?;
+
+    // Runs can end within the same second, so never overwrite an older file.
+    let mut attempt = 0;
+
+    loop {
+        let mut path = PathBuf::from(MORGUE_DIR);
+        path.push(match attempt {
+            0 => format!("morgue-{}.txt", timestamp),
+            _ => format!("morgue-{}-{}.txt", timestamp, attempt),
+        });
+
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(render(game).as_bytes())?;
+                return Ok(path);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub fn render(game: &Game) -> String {
+    let mut out = String::new();
+    let player = &game.player;
+
+    writeln!(out, "riirogue morgue file").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "{} on depth {} after {} turns.",
+        game.cause_of_death
+            .as_ref()
+            .map_or("Still alive", |c| c.as_str()),
+        game.depth,
+        game.turn
+    ).unwrap();
+
+    writeln!(out).unwrap();
+    writeln!(out, "== Character ==").unwrap();
+    writeln!(out, "Level: {} ({} XP)", player.level, player.xp).unwrap();
+    writeln!(out, "HP: {}/{}", player.hp(), player.max_hp).unwrap();
+    writeln!(out, "Attack: {}", player.atk).unwrap();
+    writeln!(out, "Gold: {}", player.gold).unwrap();
+    writeln!(out, "Deepest level: {}", game.depth).unwrap();
+    writeln!(out, "Turns: {}", game.turn).unwrap();
+
+    writeln!(out).unwrap();
+    writeln!(out, "== Inventory ==").unwrap();
+    if player.inv.is_empty() {
+        writeln!(out, "(empty)").unwrap();
+    }
+    for stack in player.inv.iter() {
+        let name = game.identification.name(stack.item());
+        writeln!(out, "{}", identification::counted(&name, stack.count())).unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "== Kills ==").unwrap();
+    for (name, count) in game.kills.iter() {
+        writeln!(out, "{:>4} {}", count, name).unwrap();
+    }
+    writeln!(out, "Total: {}", game.total_kills()).unwrap();
+
+    writeln!(out).unwrap();
+    writeln!(out, "== Last messages ==").unwrap();
+    let skip = game.events.len().saturating_sub(MORGUE_MESSAGES);
+    for event in game.events.iter().skip(skip) {
+        writeln!(out, "{}", event.msg).unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "== Map ==").unwrap();
+    out.push_str(&map_dump(game));
+
+    out
+}
+
+/// The parts of the map the player explored, one character per tile.
+fn map_dump(game: &Game) -> String {
+    let map = &game.map;
+    let mut rows = Vec::with_capacity(map.height() as usize);
+
+    for y in 0..map.height() {
+        let mut row: String = (0..map.width())
+            .map(|x| glyph(game, Coords::new(x, y)))
+            .collect();
+
+        let len = row.rfind(|c| c != ' ').map_or(0, |i| i + 1);
+        row.truncate(len);
+        rows.push(row);
+    }
+
+    while rows.last().is_some_and(|r| r.is_empty()) {
+        rows.pop();
+    }
+
+    let first = rows.iter().position(|r| !r.is_empty()).unwrap_or(0);
+
+    rows[first..]
+        .iter()
+        .fold(String::new(), |out, row| out + row + "\n")
+}
+
+fn glyph(game: &Game, pos: Coords) -> char {
+    let map = &game.map;
+
+    if !map.is_explored(pos) {
+        return ' ';
+    }
+
+    if game.player.pos == pos {
+        return '@';
+    }
+
+    if map.is_shopkeeper(pos) {
+        return '@';
+    }
+
+    if let Some(monster) = map.monster_at(pos).filter(|_| map.is_visible(pos)) {
+        return monster.tile_name().chars().next().unwrap_or('M');
+    }
+
+    if let Some(item) = map.items_at(pos).next() {
+        return match item.tile_name() {
+            "potion" => '!',
+            name if name.chars().count() == 1 => name.chars().next().unwrap(),
+            _ => '*',
+        };
+    }
+
+    if map.traps.iter().any(|t| t.pos == pos && !t.hidden) {
+        return '^';
+    }
+
+    match map.tile_at(pos).map(|t| t.tile_type) {
+        Some(TileType::Wall) => '#',
+        Some(TileType::Pathway) => '.',
+        Some(TileType::Grass) => '"',
+        Some(TileType::Door { open: true }) => '\'',
+        Some(TileType::Door { open: false }) => '+',
+        Some(ref t) if t.is_walkable_tile() => '.',
+        _ => ' ',
+    }
+}
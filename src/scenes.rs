@@ -0,0 +1,793 @@
+//! The top-level screens of the game. They live on a stack: only the topmost
+//! scene gets updates and input, while the ones below it keep being drawn for
+//! as long as the scenes above them are overlays.
+
+use entities::{self, Item};
+use game::{Game, TargetAction};
+use ggez::{
+    event::{Keycode, Mod, MouseButton}, graphics::Color,
+};
+use helpers::Coords;
+use highscores::{HighScores, Score};
+use identification;
+use messages::{Event, EventType};
+use morgue;
+use tileset::TileSet;
+use {DISPLAY_MAP_HEIGHT, DISPLAY_MAP_WIDTH, DISPLAY_SCALE_FACTOR, TILE_SIZE};
+
+/// What the scene stack should do after a scene handled something.
+pub enum Transition {
+    None,
+    Push(Box<Scene>),
+    Pop,
+    /// Swaps the topmost scene for another one.
+    Replace(Box<Scene>),
+    /// Throws the whole stack away and starts over from the given scene.
+    Reset(Box<Scene>),
+    Quit,
+}
+
+/// Everything the scenes share.
+pub struct World {
+    pub ts: TileSet,
+    pub game: Option<Game>,
+}
+
+pub trait Scene {
+    fn update(&mut self, _world: &mut World) -> Transition {
+        Transition::None
+    }
+
+    /// Queues the scene's tiles. Called every tick for every visible scene,
+    /// bottom to top.
+    fn draw(&mut self, world: &mut World);
+
+    fn key_down(
+        &mut self,
+        world: &mut World,
+        keycode: Keycode,
+        keymod: Mod,
+        repeat: bool,
+    ) -> Transition;
+
+    fn mouse_button_down(
+        &mut self,
+        _world: &mut World,
+        _button: MouseButton,
+        _x: i32,
+        _y: i32,
+    ) -> Transition {
+        Transition::None
+    }
+
+    /// Whether the scene below should still be drawn underneath this one.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// A list of entries with a cursor that wraps around at both ends.
+struct Menu {
+    entries: Vec<&'static str>,
+    cursor: usize,
+}
+
+impl Menu {
+    fn new(entries: Vec<&'static str>) -> Menu {
+        Menu { entries, cursor: 0 }
+    }
+
+    /// Moves the cursor on Up and Down. Returns whether the key was used.
+    fn key_down(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::Down => self.cursor = (self.cursor + 1) % self.entries.len(),
+            Keycode::Up => {
+                self.cursor = (self.cursor + self.entries.len() - 1) % self.entries.len()
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.entries.iter().map(|&e| String::from(e)).collect()
+    }
+}
+
+/// Draws a dark panel across the screen holding `lines`, with a `>` next to
+/// the line at `cursor`, if any.
+fn draw_panel(ts: &mut TileSet, lines: &[String], cursor: Option<usize>) {
+    let top = DISPLAY_MAP_HEIGHT / 4;
+
+    ts.queue_rect(
+        "solid",
+        (0, top),
+        (DISPLAY_MAP_WIDTH, lines.len() as i32 + 3),
+        Some(Color::from_rgba(0x00, 0x00, 0x00, 0xFA)),
+    ).unwrap();
+
+    for (i, line) in lines.iter().enumerate() {
+        ts.queue_string(
+            line,
+            (5, top + 2 + i as i32),
+            None,
+            Some(Color::from_rgba(0xFF, 0xFF, 0xFF, 0xFF)),
+        );
+    }
+
+    if let Some(cursor) = cursor {
+        ts.queue_string(
+            ">",
+            (4, top + 2 + cursor as i32),
+            None,
+            Some(Color::from_rgba(0xFF, 0xFF, 0xFF, 0xFF)),
+        );
+    }
+}
+
+pub struct TitleScene {
+    menu: Menu,
+}
+
+impl TitleScene {
+    pub fn new() -> TitleScene {
+        TitleScene {
+            menu: Menu::new(vec!["New game", "High scores", "Quit"]),
+        }
+    }
+}
+
+impl Scene for TitleScene {
+    fn draw(&mut self, world: &mut World) {
+        let mut lines = vec![String::from("RIIROGUE"), String::new()];
+        lines.extend(self.menu.lines());
+
+        draw_panel(&mut world.ts, &lines, Some(self.menu.cursor + 2));
+    }
+
+    fn key_down(
+        &mut self,
+        _world: &mut World,
+        keycode: Keycode,
+        _: Mod,
+        repeat: bool,
+    ) -> Transition {
+        if repeat || self.menu.key_down(keycode) {
+            return Transition::None;
+        }
+
+        match keycode {
+            Keycode::Return => match self.menu.cursor {
+                0 => Transition::Replace(Box::new(NewGameScene)),
+                1 => Transition::Push(Box::new(HighScoresScene::new(None))),
+                _ => Transition::Quit,
+            },
+            Keycode::Escape => Transition::Quit,
+            _ => Transition::None,
+        }
+    }
+}
+
+/// Sets up a fresh run, then hands over to the game itself.
+pub struct NewGameScene;
+
+impl Scene for NewGameScene {
+    fn update(&mut self, world: &mut World) -> Transition {
+        world.game = Some(Game::new());
+
+        Transition::Replace(Box::new(PlayingScene))
+    }
+
+    fn draw(&mut self, world: &mut World) {
+        draw_panel(&mut world.ts, &[String::from("Generating level...")], None);
+    }
+
+    fn key_down(&mut self, _: &mut World, _: Keycode, _: Mod, _: bool) -> Transition {
+        Transition::None
+    }
+}
+
+pub struct PlayingScene;
+
+impl Scene for PlayingScene {
+    fn update(&mut self, world: &mut World) -> Transition {
+        let game = match world.game {
+            Some(ref mut game) => game,
+            None => return Transition::Replace(Box::new(NewGameScene)),
+        };
+
+        game.update();
+
+        if game.is_over() {
+            Transition::Push(Box::new(GameOverScene::new(game)))
+        } else if game.shopping {
+            game.shopping = false;
+            Transition::Push(Box::new(ShopScene::new()))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn draw(&mut self, world: &mut World) {
+        if let Some(ref mut game) = world.game {
+            game.draw(&mut world.ts);
+        }
+    }
+
+    fn mouse_button_down(
+        &mut self,
+        world: &mut World,
+        button: MouseButton,
+        x: i32,
+        y: i32,
+    ) -> Transition {
+        let game = match world.game {
+            Some(ref mut game) => game,
+            None => return Transition::None,
+        };
+
+        if button != MouseButton::Left || game.targeting.is_some() || game.is_over() {
+            return Transition::None;
+        }
+
+        let tile_size = (TILE_SIZE as f32 * DISPLAY_SCALE_FACTOR) as i32;
+        let camera = game.camera();
+        let target = Coords::new(camera.x + x / tile_size, camera.y + y / tile_size);
+
+        game.look_cursor = None;
+        game.travel_to(target);
+
+        Transition::None
+    }
+
+    fn key_down(
+        &mut self,
+        world: &mut World,
+        keycode: Keycode,
+        keymod: Mod,
+        repeat: bool,
+    ) -> Transition {
+        let game = match world.game {
+            Some(ref mut game) => game,
+            None => return Transition::None,
+        };
+
+        if game.is_over() {
+            return Transition::None;
+        }
+
+        if game.activity.is_some() {
+            if !repeat {
+                game.activity = None;
+            }
+
+            return Transition::None;
+        }
+
+        if game.targeting.is_some() {
+            if !repeat {
+                game.targeting_key_down(keycode);
+            }
+
+            return Transition::None;
+        }
+
+        if !repeat {
+            #[cfg(debug_assertions)]
+            debug_key_down(game, keycode);
+
+            if let Keycode::R = keycode {
+                game.regenerate_level();
+            } else if let Keycode::M = keycode {
+                return Transition::Push(Box::new(PausedScene::new()));
+            } else if let Keycode::I = keycode {
+                game.look_cursor = None;
+                return Transition::Push(Box::new(InventoryScene::new()));
+            } else if let Keycode::L = keycode {
+                game.look_cursor = match game.look_cursor {
+                    Some(_) => None,
+                    None => Some(game.player.pos),
+                };
+            } else if let Keycode::Escape = keycode {
+                if game.look_cursor.take().is_none() {
+                    return Transition::Push(Box::new(PausedScene::new()));
+                }
+            } else if let Keycode::Return = keycode {
+                if let Some(cursor) = game.look_cursor.take() {
+                    game.travel_to(cursor);
+                }
+            } else if let Keycode::U = keycode {
+                // Quaffs a healing potion, once the player knows which one that is.
+                let pos = {
+                    let identification = &game.identification;
+
+                    game.player.inv.iter().position(|s| {
+                        s.item().id() == entities::HealingPotion.id()
+                            && identification.is_known(s.item())
+                    })
+                };
+
+                match pos {
+                    Some(pos) => {
+                        game.use_item(pos);
+                    }
+                    None => {
+                        let message = if game.identification.is_known(&entities::HealingPotion) {
+                            "You have no healing potions."
+                        } else {
+                            "You don't know which potion heals."
+                        };
+
+                        game.events.push(Event::new(String::from(message), EventType::Info));
+                    }
+                }
+            } else if let Keycode::Period = keycode {
+                game.end_turn();
+            } else if let Keycode::Z = keycode {
+                game.start_resting();
+            } else if let Keycode::X = keycode {
+                game.start_exploring();
+            } else if let Keycode::S = keycode {
+                game.search();
+                game.end_turn();
+            } else if let Keycode::C = keycode {
+                if game.close_door() {
+                    game.end_turn();
+                }
+            } else if let Keycode::F = keycode {
+                game.look_cursor = None;
+                game.start_targeting(TargetAction::Fire);
+            }
+        }
+
+        let direction = match keycode {
+            Keycode::Left => Some((-1, 0)),
+            Keycode::Right => Some((1, 0)),
+            Keycode::Up => Some((0, -1)),
+            Keycode::Down => Some((0, 1)),
+            _ => None,
+        };
+
+        if let Some((dx, dy)) = direction {
+            if game.look_cursor.is_some() {
+                game.move_look_cursor(dx, dy);
+            } else if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                game.start_run(dx, dy);
+            } else if game.move_player(dx, dy) {
+                game.end_turn();
+            }
+        }
+
+        Transition::None
+    }
+}
+
+/// Cheats for trying things out, in debug builds only: H, J and K drop items
+/// next to the player, and G a monster.
+#[cfg(debug_assertions)]
+fn debug_key_down(game: &mut Game, keycode: Keycode) {
+    use rand::{thread_rng, Rng};
+
+    let pos = Coords::new(game.player.pos.x + 1, game.player.pos.y);
+    let mut rng = thread_rng();
+
+    if game.map.tile_at(pos).is_none() {
+        return;
+    }
+
+    match keycode {
+        Keycode::H => match rng.gen_range(0, 5) {
+            0 => game.map.add_item(pos, entities::RegenerationPotion {}),
+            1 => game.map.add_item(pos, entities::HastePotion {}),
+            2 => game.map.add_item(pos, entities::ConfusionPotion {}),
+            3 => game.map.add_item(pos, entities::Ration {}),
+            _ => game.map.add_item(pos, entities::HealingPotion {}),
+        },
+        Keycode::J => match rng.gen_range(0, 5) {
+            0 => game.map.add_item(pos, entities::TeleportScroll {}),
+            1 => game.map.add_item(pos, entities::MagicMappingScroll {}),
+            2 => game.map.add_item(pos, entities::EnchantWeaponScroll {}),
+            3 => game.map.add_item(pos, entities::WandOfFireBolt { charges: 4 }),
+            _ => game.map.add_item(pos, entities::WandOfSlowMonster { charges: 4 }),
+        },
+        Keycode::K => game.map.add_item(pos, entities::ThrowingKnife {}),
+        Keycode::G => {
+            if rng.gen() {
+                game.map.add_creature(entities::Goblin::new(pos));
+            } else {
+                game.map.add_creature(entities::CaveSpider::new(pos));
+            }
+        }
+        _ => {}
+    }
+}
+
+pub struct InventoryScene {
+    cursor: usize,
+}
+
+impl InventoryScene {
+    pub fn new() -> InventoryScene {
+        InventoryScene { cursor: 0 }
+    }
+}
+
+impl Scene for InventoryScene {
+    fn draw(&mut self, world: &mut World) {
+        let game = match world.game {
+            Some(ref game) => game,
+            None => return,
+        };
+        let inv = &game.player.inv;
+
+        let mut lines = vec![String::from("Inventory"), String::new()];
+
+        if inv.is_empty() {
+            lines.push(String::from("(empty)"));
+        }
+
+        lines.extend(inv.iter().map(|s| {
+            identification::counted(&game.identification.name(s.item()), s.count())
+        }));
+        lines.push(String::new());
+        lines.push(String::from("[Enter] Use  [D] Drop  [T] Throw"));
+        lines.push(String::from("[Esc] Close"));
+
+        let cursor = if inv.is_empty() {
+            None
+        } else {
+            Some(self.cursor + 2)
+        };
+
+        draw_panel(&mut world.ts, &lines, cursor);
+    }
+
+    fn key_down(
+        &mut self,
+        world: &mut World,
+        keycode: Keycode,
+        _: Mod,
+        repeat: bool,
+    ) -> Transition {
+        let game = match world.game {
+            Some(ref mut game) => game,
+            None => return Transition::Pop,
+        };
+
+        if repeat {
+            return Transition::None;
+        }
+
+        let len = game.player.inv.len();
+
+        match keycode {
+            Keycode::Down if len > 0 => self.cursor = (self.cursor + 1) % len,
+            Keycode::Up if len > 0 => self.cursor = (self.cursor + len - 1) % len,
+            Keycode::Return if game.use_item(self.cursor) => return Transition::Pop,
+            Keycode::D if game.drop_item(self.cursor) => return Transition::Pop,
+            Keycode::T if self.cursor < len => {
+                game.start_targeting(TargetAction::Throw(self.cursor));
+                return Transition::Pop;
+            }
+            Keycode::Escape | Keycode::I => return Transition::Pop,
+            _ => {}
+        }
+
+        Transition::None
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// Trading with a shopkeeper. Lists either the shop's stock or the player's
+/// inventory, with what each item goes for.
+pub struct ShopScene {
+    cursor: usize,
+    selling: bool,
+}
+
+impl ShopScene {
+    pub fn new() -> ShopScene {
+        ShopScene {
+            cursor: 0,
+            selling: false,
+        }
+    }
+
+    /// How many entries the current list has.
+    fn len(&self, game: &Game) -> usize {
+        if self.selling {
+            game.player.inv.len()
+        } else {
+            game.map.shop.as_ref().map_or(0, |s| s.stock.len())
+        }
+    }
+}
+
+impl Scene for ShopScene {
+    fn draw(&mut self, world: &mut World) {
+        let game = match world.game {
+            Some(ref game) => game,
+            None => return,
+        };
+        let shop = match game.map.shop {
+            Some(ref shop) => shop,
+            None => return,
+        };
+
+        let mut lines = vec![
+            format!(
+                "{}  Gold: {}",
+                if self.selling { "Selling" } else { "Buying" },
+                game.player.gold
+            ),
+            String::new(),
+        ];
+
+        let entries: Vec<String> = if self.selling {
+            game.player
+                .inv
+                .iter()
+                .map(|s| {
+                    format!(
+                        "{} ({} gold)",
+                        identification::counted(&game.identification.name(s.item()), s.count()),
+                        shop.offer(s.item())
+                    )
+                })
+                .collect()
+        } else {
+            shop.stock
+                .iter()
+                .map(|i| format!("{} ({} gold)", game.identification.name(&**i), shop.price(&**i)))
+                .collect()
+        };
+
+        let cursor = if entries.is_empty() {
+            lines.push(String::from("(nothing)"));
+            None
+        } else {
+            Some(self.cursor + 2)
+        };
+
+        lines.extend(entries);
+        lines.push(String::new());
+        lines.push(String::from(if self.selling {
+            "[Enter] Sell  [Tab] Buy"
+        } else {
+            "[Enter] Buy  [Tab] Sell"
+        }));
+        lines.push(String::from("[Esc] Close"));
+
+        draw_panel(&mut world.ts, &lines, cursor);
+    }
+
+    fn key_down(
+        &mut self,
+        world: &mut World,
+        keycode: Keycode,
+        _: Mod,
+        repeat: bool,
+    ) -> Transition {
+        let game = match world.game {
+            Some(ref mut game) => game,
+            None => return Transition::Pop,
+        };
+
+        if repeat {
+            return Transition::None;
+        }
+
+        let len = self.len(game);
+
+        match keycode {
+            Keycode::Down if len > 0 => self.cursor = (self.cursor + 1) % len,
+            Keycode::Up if len > 0 => self.cursor = (self.cursor + len - 1) % len,
+            Keycode::Tab => {
+                self.selling = !self.selling;
+                self.cursor = 0;
+            }
+            Keycode::Return => {
+                if self.selling {
+                    game.sell_item(self.cursor);
+                } else {
+                    game.buy_item(self.cursor);
+                }
+
+                self.cursor = self.cursor.min(self.len(game).saturating_sub(1));
+            }
+            Keycode::Escape => return Transition::Pop,
+            _ => {}
+        }
+
+        Transition::None
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+pub struct PausedScene {
+    menu: Menu,
+}
+
+impl PausedScene {
+    pub fn new() -> PausedScene {
+        PausedScene {
+            menu: Menu::new(vec!["Resume", "New game", "Quit to title"]),
+        }
+    }
+}
+
+impl Scene for PausedScene {
+    fn draw(&mut self, world: &mut World) {
+        let mut lines = vec![String::from("Paused"), String::new()];
+        lines.extend(self.menu.lines());
+
+        draw_panel(&mut world.ts, &lines, Some(self.menu.cursor + 2));
+    }
+
+    fn key_down(
+        &mut self,
+        _world: &mut World,
+        keycode: Keycode,
+        _: Mod,
+        repeat: bool,
+    ) -> Transition {
+        if repeat || self.menu.key_down(keycode) {
+            return Transition::None;
+        }
+
+        match keycode {
+            Keycode::Return => match self.menu.cursor {
+                0 => Transition::Pop,
+                1 => Transition::Reset(Box::new(NewGameScene)),
+                _ => Transition::Reset(Box::new(TitleScene::new())),
+            },
+            Keycode::Escape | Keycode::M => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+pub struct GameOverScene {
+    /// Where the morgue file went, for showing on screen.
+    morgue: String,
+    /// The run's place in the high score table, if it got one.
+    rank: Option<usize>,
+}
+
+impl GameOverScene {
+    /// Writes the morgue file for the run that just ended and records its
+    /// score.
+    pub fn new(game: &Game) -> GameOverScene {
+        let morgue = match morgue::write(game) {
+            Ok(path) => format!(
+                "Saved {}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            Err(e) => {
+                println!("Couldn't write morgue file: {}", e);
+                String::from("Couldn't write morgue file.")
+            }
+        };
+
+        let mut high_scores = HighScores::load();
+        let rank = high_scores.add(Score::from_game(game));
+
+        if rank.is_some() {
+            if let Err(e) = high_scores.save() {
+                println!("Couldn't save high scores: {}", e);
+            }
+        }
+
+        GameOverScene { morgue, rank }
+    }
+}
+
+impl Scene for GameOverScene {
+    fn draw(&mut self, world: &mut World) {
+        let lines = match world.game {
+            Some(ref game) => vec![
+                String::from("You died."),
+                String::new(),
+                game.cause_of_death.clone().unwrap_or_default(),
+                format!("Depth: {}", game.depth),
+                format!("Turns: {}", game.turn),
+                format!("Kills: {}", game.total_kills()),
+                format!("Score: {}", Score::from_game(game).score),
+                match self.rank {
+                    Some(rank) => format!("New high score! Rank {}", rank + 1),
+                    None => String::new(),
+                },
+                String::new(),
+                self.morgue.clone(),
+                String::new(),
+                String::from("[Enter] New game  [Esc] Title"),
+                String::from("[S] High scores"),
+            ],
+            None => return,
+        };
+
+        draw_panel(&mut world.ts, &lines, None);
+    }
+
+    fn key_down(
+        &mut self,
+        _: &mut World,
+        keycode: Keycode,
+        _: Mod,
+        repeat: bool,
+    ) -> Transition {
+        if repeat {
+            return Transition::None;
+        }
+
+        match keycode {
+            Keycode::Return => Transition::Reset(Box::new(NewGameScene)),
+            Keycode::Escape => Transition::Reset(Box::new(TitleScene::new())),
+            Keycode::S => Transition::Push(Box::new(HighScoresScene::new(self.rank))),
+            _ => Transition::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+pub struct HighScoresScene {
+    high_scores: HighScores,
+    /// The entry to point out, like the run that just ended.
+    highlight: Option<usize>,
+}
+
+impl HighScoresScene {
+    pub fn new(highlight: Option<usize>) -> HighScoresScene {
+        HighScoresScene {
+            high_scores: HighScores::load(),
+            highlight,
+        }
+    }
+}
+
+impl Scene for HighScoresScene {
+    fn draw(&mut self, world: &mut World) {
+        let mut lines = vec![String::from("High scores"), String::new()];
+
+        if self.high_scores.entries.is_empty() {
+            lines.push(String::from("No runs yet."));
+        }
+
+        lines.extend(
+            self.high_scores
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(i, s)| format!("{:>2}. {:>5} D{} {}", i + 1, s.score, s.depth, s.cause)),
+        );
+        lines.push(String::new());
+        lines.push(String::from("[Esc] Back"));
+
+        draw_panel(&mut world.ts, &lines, self.highlight.map(|i| i + 2));
+    }
+
+    fn key_down(&mut self, _: &mut World, keycode: Keycode, _: Mod, repeat: bool) -> Transition {
+        match keycode {
+            Keycode::Escape | Keycode::Return if !repeat => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
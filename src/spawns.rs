@@ -0,0 +1,156 @@
+//! Spawn tables: which monsters live on a level, and which ones wander in
+//! later.
+//!
+//! Spawn tables are weighted and keyed by depth, see `tables`. An entry can be
+//! a whole group of monsters that turn up together.
+
+use difficulty::Difficulty;
+use entities::{self, Creature};
+use helpers::{Coords, Rect};
+use map::Map;
+use rand::Rng;
+use tables;
+
+/// A kind of monster that can be spawned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Monster {
+    Goblin,
+    CaveSpider,
+}
+
+impl Monster {
+    /// Makes the monster, toughened up for the given difficulty.
+    pub fn create(&self, pos: Coords, difficulty: &Difficulty) -> Box<Creature> {
+        match self {
+            Monster::Goblin => {
+                let base = entities::Goblin::new(pos);
+
+                Box::new(entities::Goblin {
+                    hp: difficulty.monster_hp(base.hp),
+                    atk: difficulty.monster_atk(base.atk),
+                    ..base
+                })
+            }
+            Monster::CaveSpider => {
+                let base = entities::CaveSpider::new(pos);
+
+                Box::new(entities::CaveSpider {
+                    hp: difficulty.monster_hp(base.hp),
+                    atk: difficulty.monster_atk(base.atk),
+                    ..base
+                })
+            }
+        }
+    }
+}
+
+/// What a spawn table can hand out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spawn {
+    /// Leaves the room empty.
+    Nothing,
+    Single(Monster),
+    /// Between the given smallest and largest number of one kind of monster,
+    /// close together.
+    Group(Monster, u32, u32),
+}
+
+impl Spawn {
+    /// The monsters that make up the spawn.
+    pub fn monsters(&self, rng: &mut impl Rng) -> Vec<Monster> {
+        match *self {
+            Spawn::Nothing => Vec::new(),
+            Spawn::Single(monster) => vec![monster],
+            Spawn::Group(monster, min, max) => {
+                vec![monster; rng.gen_range(min, max + 1) as usize]
+            }
+        }
+    }
+}
+
+pub type Entry = tables::Entry<Spawn>;
+
+/// What lives in the rooms of a freshly generated level.
+pub const ROOM: &[Entry] = &[
+    (Spawn::Nothing, 10, 1),
+    (Spawn::Single(Monster::Goblin), 6, 1),
+    (Spawn::Single(Monster::CaveSpider), 4, 1),
+    (Spawn::Group(Monster::CaveSpider, 2, 3), 2, 2),
+    (Spawn::Group(Monster::Goblin, 2, 4), 2, 2),
+    (Spawn::Group(Monster::Goblin, 3, 6), 1, 4),
+];
+
+/// What wanders in while the player is on a level.
+pub const WANDERING: &[Entry] = &[
+    (Spawn::Single(Monster::Goblin), 3, 1),
+    (Spawn::Single(Monster::CaveSpider), 2, 1),
+    (Spawn::Group(Monster::Goblin, 2, 3), 1, 3),
+];
+
+/// Picks an entry of `table` fit for the difficulty's depth.
+pub fn roll(table: &[Entry], difficulty: &Difficulty, rng: &mut impl Rng) -> Spawn {
+    tables::roll(table, difficulty.depth, rng).unwrap_or(Spawn::Nothing)
+}
+
+/// Puts the monsters of `spawn` on free floor tiles inside `room`.
+pub fn populate_room(
+    map: &mut Map,
+    room: Rect,
+    spawn: Spawn,
+    difficulty: &Difficulty,
+    rng: &mut impl Rng,
+) {
+    for monster in spawn.monsters(rng) {
+        // Small rooms can fill up, so give up on a monster after a few tries.
+        for _ in 0..10 {
+            let pos = Coords::new(
+                rng.gen_range(room.left() + 1, room.right()),
+                rng.gen_range(room.top() + 1, room.bottom()),
+            );
+
+            // The player isn't on the level yet while it's being generated.
+            if is_free(map, pos, None) {
+                map.monsters.push(monster.create(pos, difficulty));
+                break;
+            }
+        }
+    }
+}
+
+/// Puts the monsters of `spawn` around `pos`, as close as there is room
+/// for, on tiles out of the sight of the player at `player`.
+pub fn place_near(
+    map: &mut Map,
+    pos: Coords,
+    player: Coords,
+    spawn: Spawn,
+    difficulty: &Difficulty,
+    rng: &mut impl Rng,
+) {
+    for monster in spawn.monsters(rng) {
+        let spot = (0..=2)
+            .flat_map(|radius| {
+                (-radius..=radius).flat_map(move |dy| {
+                    (-radius..=radius).map(move |dx| Coords::new(pos.x + dx, pos.y + dy))
+                })
+            })
+            .find(|&c| is_free(map, c, Some(player)) && !map.is_visible(c));
+
+        if let Some(spot) = spot {
+            map.monsters.push(monster.create(spot, difficulty));
+        }
+    }
+}
+
+/// Whether a monster can be put at `pos`: a walkable tile outside the shop,
+/// with nobody standing on it.
+fn is_free(map: &Map, pos: Coords, player: Option<Coords>) -> bool {
+    let in_shop = map.shop
+        .as_ref()
+        .is_some_and(|s| s.room.contains((pos.x, pos.y)));
+
+    map.tile_at(pos)
+        .is_some_and(|t| t.tile_type.is_walkable_tile())
+        && map.monster_at(pos).is_none() && !map.is_shopkeeper(pos) && !in_shop
+        && player != Some(pos)
+}
@@ -0,0 +1,44 @@
+//! Weighted tables keyed by depth, the shape shared by loot and spawn tables.
+
+use rand::{
+    distributions::{Distribution, Weighted, WeightedChoice}, Rng,
+};
+
+/// One row of a table: what turns up, how often compared to the other rows,
+/// and the shallowest depth it turns up at.
+pub type Entry<T> = (T, u32, i32);
+
+/// Picks a row of `table` that turns up at `depth`, weighted by how common
+/// each row is. Gives `None` if no row fits.
+pub fn roll<T: Copy>(table: &[Entry<T>], depth: i32, rng: &mut impl Rng) -> Option<T> {
+    let mut choices: Vec<_> = table
+        .iter()
+        .filter(|&&(_, weight, min_depth)| weight > 0 && min_depth <= depth)
+        .map(|&(item, weight, _)| Weighted { weight, item })
+        .collect();
+
+    if choices.is_empty() {
+        return None;
+    }
+
+    Some(WeightedChoice::new(&mut choices).sample(rng))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn roll_skips_rows_deeper_than_depth() {
+        let table = [('a', 1, 1), ('b', 100, 3), ('c', 0, 1)];
+        let mut rng = thread_rng();
+
+        for _ in 0..100 {
+            assert_eq!(roll(&table, 1, &mut rng), Some('a'));
+        }
+
+        assert_eq!(roll(&table, 0, &mut rng), None);
+        assert!((0..100).any(|_| roll(&table, 3, &mut rng) == Some('b')));
+    }
+}
@@ -119,6 +119,38 @@ impl TileSet {
         Ok(())
     }
 
+    /// Queues `text` one glyph per tile, left to right from `origin`, on a
+    /// black background unless told otherwise.
+    pub fn queue_string(
+        &mut self,
+        text: &str,
+        origin: (i32, i32),
+        background_color: Option<graphics::Color>,
+        foreground_color: Option<graphics::Color>,
+    ) {
+        let mut s = String::with_capacity(1);
+
+        for (origin_offset, c) in text.chars().enumerate() {
+            s.push(c);
+
+            if let Err(s) = self.queue_tile_with_background(
+                "solid",
+                &s,
+                (origin.0 + origin_offset as i32, origin.1),
+                if background_color.is_none() {
+                    Some(graphics::Color::from_rgb(0, 0, 0))
+                } else {
+                    background_color
+                },
+                foreground_color,
+            ) {
+                println!("`{}` not found", s);
+            }
+
+            s.pop();
+        }
+    }
+
     pub fn clear_queue(&mut self) {
         self.sprite_batch.clear();
     }